@@ -1,7 +1,9 @@
 use wasm_bindgen::prelude::*;
 
 use crate::{
+    garbage::GARBAGE_COLOR,
     pieces::{PieceState, PieceType},
+    rotation::{RotationSystem, Srs},
     utils,
 };
 
@@ -27,7 +29,7 @@ impl Board {
         Board {
             width: 10,
             height: 20,
-            current_piece: PieceState::new(PieceType::Straight, 0),
+            current_piece: PieceState::new(PieceType::Straight, Srs.piece(&PieceType::Straight), 0),
             placed_pieces: vec![vec![None; 10]; 20],
         }
     }
@@ -42,21 +44,59 @@ impl Board {
 }
 
 impl Board {
+    pub fn cell(&self, row: usize, col: usize) -> Option<&str> {
+        self.placed_pieces[row][col].as_deref()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.placed_pieces.iter().flatten().all(Option::is_none)
+    }
+
+    /// Rows that still hold any garbage.
+    pub fn garbage_rows(&self) -> usize {
+        self.placed_pieces
+            .iter()
+            .filter(|row| row.iter().flatten().any(|color| color == GARBAGE_COLOR))
+            .count()
+    }
+
+    /// Pushes the stack up by one row for each of `holes` and fills the
+    /// bottom with garbage, open at those columns. Returns `false` if any
+    /// blocks were pushed above the field and lost.
+    pub fn insert_garbage_rows(&mut self, holes: &[usize]) -> bool {
+        let mut fits = true;
+        for &hole in holes {
+            let top = self.placed_pieces.remove(0);
+            fits &= top.iter().all(Option::is_none);
+
+            let row = (0..self.width as usize)
+                .map(|col| Some(GARBAGE_COLOR.to_string()).filter(|_| col != hole))
+                .collect();
+            self.placed_pieces.push(row);
+        }
+        fits
+    }
+
+    /// Whether a block may occupy `(row, col)`. Rows above the field are open.
+    pub fn is_open_cell(&self, row: i8, col: i8) -> bool {
+        if col < 0 || col >= self.width as i8 || row >= self.height as i8 {
+            return false;
+        }
+        row < 0 || self.placed_pieces[row as usize][col as usize].is_none()
+    }
+
     pub(crate) fn is_valid_position(&self, piece: &PieceState) -> bool {
-        piece.iter_blocks().all(|(r, c)| {
-            if c < 0 || c >= self.width as i8 || r >= self.height as i8 {
-                return false;
-            }
-            if r >= 0 && self.placed_pieces[r as usize][c as usize].is_some() {
-                return false;
-            }
-            true
-        })
+        piece.iter_blocks().all(|(r, c)| self.is_open_cell(r, c))
     }
 
+    /// Places the current piece's blocks. The engine tops out instead of
+    /// locking a piece with blocks above the field.
     pub(crate) fn lock_piece(&mut self) {
         let color = self.current_piece.color();
         for (r, c) in self.current_piece.iter_blocks() {
+            if r < 0 {
+                continue;
+            }
             self.placed_pieces[r as usize][c as usize] = Some(color.to_string());
         }
     }
@@ -0,0 +1,193 @@
+//! Little binary and text encoding helpers shared by replays and saved
+//! games. Varints are unsigned LEB128; floats are little-endian `f64`.
+
+use std::convert::TryFrom;
+
+use crate::{pieces::PieceType, random::RandomizerKind, rotation::RotationSystemKind};
+
+pub(crate) fn randomizer_code(randomizer: RandomizerKind) -> u8 {
+    match randomizer {
+        RandomizerKind::SevenBag => 0,
+        RandomizerKind::FourteenBag => 1,
+        RandomizerKind::PureRandom => 2,
+        RandomizerKind::Nes => 3,
+        RandomizerKind::TgmHistory => 4,
+    }
+}
+
+pub(crate) fn randomizer_from_code(code: u8) -> Result<RandomizerKind, String> {
+    match code {
+        0 => Ok(RandomizerKind::SevenBag),
+        1 => Ok(RandomizerKind::FourteenBag),
+        2 => Ok(RandomizerKind::PureRandom),
+        3 => Ok(RandomizerKind::Nes),
+        4 => Ok(RandomizerKind::TgmHistory),
+        _ => Err(format!("Unknown randomizer {}", code)),
+    }
+}
+
+pub(crate) fn rotation_system_code(rotation_system: RotationSystemKind) -> u8 {
+    match rotation_system {
+        RotationSystemKind::Srs => 0,
+        RotationSystemKind::Ars => 1,
+        RotationSystemKind::Nes => 2,
+    }
+}
+
+pub(crate) fn rotation_system_from_code(code: u8) -> Result<RotationSystemKind, String> {
+    match code {
+        0 => Ok(RotationSystemKind::Srs),
+        1 => Ok(RotationSystemKind::Ars),
+        2 => Ok(RotationSystemKind::Nes),
+        _ => Err(format!("Unknown rotation system {}", code)),
+    }
+}
+
+pub(crate) fn write_varint(bytes: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        bytes.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+pub(crate) fn write_f64(bytes: &mut Vec<u8>, value: f64) {
+    bytes.extend_from_slice(&value.to_le_bytes());
+}
+
+pub(crate) fn write_string(bytes: &mut Vec<u8>, value: &str) {
+    write_varint(bytes, value.len() as u64);
+    bytes.extend_from_slice(value.as_bytes());
+}
+
+/// Piece types are numbered by their position in `PieceType::ALL`.
+pub(crate) fn write_piece_type(bytes: &mut Vec<u8>, piece_type: &PieceType) {
+    let code = PieceType::ALL
+        .iter()
+        .position(|other| other == piece_type)
+        .expect("Expected every piece type to be in `PieceType::ALL`");
+    bytes.push(code as u8);
+}
+
+/// Reads values written by the `write_*` functions, failing on truncated or
+/// malformed data instead of panicking.
+pub(crate) struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    pub(crate) fn new(bytes: &'a [u8]) -> Reader<'a> {
+        Reader { bytes, position: 0 }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.position == self.bytes.len()
+    }
+
+    pub(crate) fn take(&mut self, len: usize) -> Result<&'a [u8], String> {
+        let end = self
+            .position
+            .checked_add(len)
+            .filter(|&end| end <= self.bytes.len())
+            .ok_or("Data is truncated")?;
+        let taken = &self.bytes[self.position..end];
+        self.position = end;
+        Ok(taken)
+    }
+
+    pub(crate) fn array<const N: usize>(&mut self) -> Result<[u8; N], String> {
+        let mut array = [0; N];
+        array.copy_from_slice(self.take(N)?);
+        Ok(array)
+    }
+
+    pub(crate) fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub(crate) fn f64(&mut self) -> Result<f64, String> {
+        Ok(f64::from_le_bytes(self.array()?))
+    }
+
+    pub(crate) fn varint(&mut self) -> Result<u64, String> {
+        let mut value: u64 = 0;
+        for shift in (0..64).step_by(7) {
+            let byte = self.u8()?;
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err("Varint is too long".to_string())
+    }
+
+    pub(crate) fn varint_u32(&mut self) -> Result<u32, String> {
+        let value = self.varint()?;
+        u32::try_from(value).map_err(|_| format!("Value {} is out of range", value))
+    }
+
+    pub(crate) fn i8(&mut self) -> Result<i8, String> {
+        Ok(self.u8()? as i8)
+    }
+
+    pub(crate) fn bool(&mut self) -> Result<bool, String> {
+        match self.u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            value => Err(format!("Expected a bool, found {}", value)),
+        }
+    }
+
+    pub(crate) fn string(&mut self) -> Result<String, String> {
+        let len = self.varint()? as usize;
+        String::from_utf8(self.take(len)?.to_vec()).map_err(|err| err.to_string())
+    }
+
+    pub(crate) fn piece_type(&mut self) -> Result<PieceType, String> {
+        let code = self.u8()?;
+        PieceType::ALL
+            .get(usize::from(code))
+            .cloned()
+            .ok_or_else(|| format!("Unknown piece type {}", code))
+    }
+}
+
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+pub(crate) fn base64_encode(bytes: &[u8]) -> String {
+    let mut text = String::with_capacity((bytes.len() * 4).div_ceil(3));
+    for chunk in bytes.chunks(3) {
+        let word = chunk.iter().enumerate().fold(0u32, |word, (i, &byte)| {
+            word | u32::from(byte) << (16 - 8 * i)
+        });
+        for i in 0..=chunk.len() {
+            let index = (word >> (18 - 6 * i)) & 0x3f;
+            text.push(BASE64_ALPHABET[index as usize] as char);
+        }
+    }
+    text
+}
+
+pub(crate) fn base64_decode(text: &str) -> Result<Vec<u8>, String> {
+    if text.len() % 4 == 1 {
+        return Err("Truncated base64".to_string());
+    }
+    let mut bytes = Vec::with_capacity(text.len() * 3 / 4);
+    let mut word: u32 = 0;
+    let mut bits = 0;
+    for character in text.bytes() {
+        let value = BASE64_ALPHABET
+            .iter()
+            .position(|&other| other == character)
+            .ok_or_else(|| format!("Invalid base64 character `{}`", character as char))?;
+        word = word << 6 | value as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((word >> bits) as u8);
+        }
+    }
+    Ok(bytes)
+}
@@ -6,10 +6,18 @@ use web_sys::{CanvasRenderingContext2d, Element, HtmlCanvasElement, HtmlElement,
 
 use crate::{
     board::Board,
-    pieces::{PieceState, PieceType},
+    engine::Stats,
+    keybinds::{self, Action, KeyBindings},
+    leaderboard::HighScore,
+    mode::GameMode,
+    pieces::PieceState,
     utils::sleep,
 };
 
+/// The size, in canvas pixels, of each piece in the next queue preview.
+const NEXT_SLOT_WIDTH: u32 = 150;
+const NEXT_SLOT_HEIGHT: u32 = 60;
+
 #[wasm_bindgen]
 pub struct Display {
     canvas: HtmlCanvasElement,
@@ -44,8 +52,8 @@ impl Display {
     pub fn draw(
         &self,
         board: &Board,
-        held_piece: &Option<PieceType>,
-        next_piece_type: &PieceType,
+        held_piece: Option<&PieceState>,
+        next_pieces: &[PieceState],
         ghost_piece: &PieceState,
     ) {
         self.context.clear_rect(
@@ -59,15 +67,34 @@ impl Display {
         self.draw_piece(board)
             .expect("Expected `draw_piece` call to succeed");
 
-        if let Some(piece_type) = held_piece {
-            self.draw_held_piece(piece_type)
+        if let Some(piece_state) = held_piece {
+            self.draw_held_piece(piece_state)
                 .expect("Expected `draw_held_piece` call to succeed");
         }
 
         self.draw_ghost_piece(ghost_piece)
             .expect("Expected `draw_ghost_piece` call to succeed");
-        self.draw_next_piece(next_piece_type)
-            .expect("Expected `draw_next_piece` call to succeed");
+        self.draw_next_pieces(next_pieces)
+            .expect("Expected `draw_next_pieces` call to succeed");
+    }
+
+    /// Dims the playfield and labels it as paused. The pause menu's buttons
+    /// sit on top of this in the page.
+    pub fn draw_pause_overlay(&self) -> Result<(), JsValue> {
+        let width: f64 = self.canvas.width().into();
+        let height: f64 = self.canvas.height().into();
+
+        self.context.set_fill_style_str("rgba(25, 24, 26, 0.8)");
+        self.context.fill_rect(0.0, 0.0, width, height);
+
+        self.context.set_fill_style_str("#FC9867");
+        self.context
+            .set_font(&format!("bold {}px monospace", self.cell_size * 3 / 2));
+        self.context.set_text_align("center");
+        self.context
+            .fill_text("PAUSED", width / 2.0, height / 3.0)?;
+
+        Ok(())
     }
 
     fn draw_ghost_piece(&self, piece_state: &PieceState) -> Result<(), JsValue> {
@@ -163,7 +190,7 @@ impl Display {
         Ok(())
     }
 
-    fn draw_held_piece(&self, held_piece_type: &PieceType) -> Result<(), JsValue> {
+    fn draw_held_piece(&self, piece_state: &PieceState) -> Result<(), JsValue> {
         let window = web_sys::window().expect("no global `window` exists");
         let document = window.document().expect("should have a document on window");
         let held_canvas = document
@@ -184,8 +211,6 @@ impl Display {
             held_canvas.height().into(),
         );
 
-        let piece_state = PieceState::new(held_piece_type.clone(), 0);
-
         held_context.begin_path();
 
         let color = format!("--{}", piece_state.color());
@@ -242,7 +267,7 @@ impl Display {
         Ok(())
     }
 
-    fn draw_next_piece(&self, next_piece_type: &PieceType) -> Result<(), JsValue> {
+    fn draw_next_pieces(&self, next_pieces: &[PieceState]) -> Result<(), JsValue> {
         let window = web_sys::window().expect("no global `window` exists");
         let document = window.document().expect("should have a document on window");
         let next_canvas = document
@@ -251,76 +276,89 @@ impl Display {
             .dyn_into::<HtmlCanvasElement>()
             .expect("Expected `dyn_into` cast to succeed");
 
-        let held_context = next_canvas
+        let canvas_height = NEXT_SLOT_HEIGHT * next_pieces.len() as u32;
+        if next_canvas.width() != NEXT_SLOT_WIDTH || next_canvas.height() != canvas_height {
+            next_canvas.set_width(NEXT_SLOT_WIDTH);
+            next_canvas.set_height(canvas_height);
+        }
+
+        let next_context = next_canvas
             .get_context("2d")?
             .expect("Expected 2d context")
             .dyn_into::<CanvasRenderingContext2d>()?;
 
-        held_context.clear_rect(
+        next_context.clear_rect(
             0.0,
             0.0,
             next_canvas.width().into(),
             next_canvas.height().into(),
         );
 
-        let piece_state = PieceState::new(next_piece_type.clone(), 0);
-
-        held_context.begin_path();
-
-        let color = format!("--{}", piece_state.color());
+        let fill_color = window
+            .get_computed_style(&document.document_element().unwrap())?
+            .unwrap();
+
+        for (slot, piece_state) in next_pieces.iter().enumerate() {
+            next_context.begin_path();
+
+            let color = format!("--{}", piece_state.color());
+            next_context.set_fill_style_str(
+                fill_color
+                    .get_property_value(color.as_str())
+                    .unwrap()
+                    .as_str(),
+            );
+
+            let mut min_r = 4;
+            let mut max_r = 0;
+            let mut min_c = 4;
+            let mut max_c = 0;
+
+            for (r, c) in piece_state.iter_blocks() {
+                min_r = cmp::min(min_r, r);
+                max_r = cmp::max(max_r, r);
+                min_c = cmp::min(min_c, c);
+                max_c = cmp::max(max_c, c);
+            }
 
-        let fill_color = window.get_computed_style(&document.document_element().unwrap())?;
+            let piece_width_cells = max_c - min_c + 1;
+            let piece_height_cells = max_r - min_r + 1;
 
-        held_context.set_fill_style_str(
-            fill_color
-                .unwrap()
-                .get_property_value(color.as_str())
-                .unwrap()
-                .as_str(),
-        );
+            // Leave a gap between stacked pieces.
+            let slot_width = NEXT_SLOT_WIDTH as f64 * 0.8;
+            let slot_height = NEXT_SLOT_HEIGHT as f64 * 0.8;
 
-        let mut min_r = 4;
-        let mut max_r = 0;
-        let mut min_c = 4;
-        let mut max_c = 0;
+            let scale_x = slot_width / (piece_width_cells as f64 * self.cell_size as f64);
+            let scale_y = slot_height / (piece_height_cells as f64 * self.cell_size as f64);
+            let scale_factor = scale_x.min(scale_y);
 
-        for (r, c) in piece_state.iter_blocks() {
-            min_r = cmp::min(min_r, r);
-            max_r = cmp::max(max_r, r);
-            min_c = cmp::min(min_c, c);
-            max_c = cmp::max(max_c, c);
-        }
+            let scaled_cell_size = self.cell_size as f64 * scale_factor;
 
-        let piece_width_cells = max_c - min_c + 1;
-        let piece_height_cells = max_r - min_r + 1;
+            let scaled_piece_width = piece_width_cells as f64 * scaled_cell_size;
+            let scaled_piece_height = piece_height_cells as f64 * scaled_cell_size;
 
-        let scale_x =
-            next_canvas.width() as f64 / (piece_width_cells as f64 * self.cell_size as f64);
-        let scale_y =
-            next_canvas.height() as f64 / (piece_height_cells as f64 * self.cell_size as f64);
-        let scale_factor = scale_x.min(scale_y);
+            let offset_x = (NEXT_SLOT_WIDTH as f64 - scaled_piece_width) / 2.0;
+            let offset_y = (NEXT_SLOT_HEIGHT as f64 - scaled_piece_height) / 2.0
+                + (slot as u32 * NEXT_SLOT_HEIGHT) as f64;
 
-        let scaled_cell_size = self.cell_size as f64 * scale_factor;
-
-        let scaled_piece_width = piece_width_cells as f64 * scaled_cell_size;
-        let scaled_piece_height = piece_height_cells as f64 * scaled_cell_size;
-
-        let offset_x = (next_canvas.width() as f64 - scaled_piece_width) / 2.0;
-        let offset_y = (next_canvas.height() as f64 - scaled_piece_height) / 2.0;
-
-        for (r, c) in piece_state.iter_blocks() {
-            held_context.fill_rect(
-                ((c - min_c) as f64 * scaled_cell_size) + offset_x,
-                ((r - min_r) as f64 * scaled_cell_size) + offset_y,
-                scaled_cell_size,
-                scaled_cell_size,
-            )
+            for (r, c) in piece_state.iter_blocks() {
+                next_context.fill_rect(
+                    ((c - min_c) as f64 * scaled_cell_size) + offset_x,
+                    ((r - min_r) as f64 * scaled_cell_size) + offset_y,
+                    scaled_cell_size,
+                    scaled_cell_size,
+                )
+            }
+            next_context.fill();
         }
-        held_context.fill();
 
         Ok(())
     }
 
+    pub fn cell_size(&self) -> u32 {
+        self.cell_size
+    }
+
     pub fn resize(&mut self, board: &Board) -> Result<(), JsValue> {
         let window = web_sys::window().expect("no global `window` exists");
         let document = window.document().expect("should have a document on window");
@@ -330,7 +368,31 @@ impl Display {
             .expect("Expected `.game-container` element");
 
         let available_width = game_container.client_width() as u32;
-        let available_height = game_container.client_height() as u32;
+        let mut available_height = game_container.client_height() as u32;
+
+        // The mobile layout stacks the sidebars and touch controls around the
+        // playfield, so only what they leave over is available to it.
+        let style = window.get_computed_style(&game_container)?;
+        if let Some(style) = style {
+            if style.get_property_value("flex-direction")? == "column" {
+                let gap = style
+                    .get_property_value("row-gap")?
+                    .trim_end_matches("px")
+                    .parse::<f64>()
+                    .unwrap_or(0.0) as u32;
+                let children = game_container.children();
+                for index in 0..children.length() {
+                    let child = children.item(index).unwrap().dyn_into::<HtmlElement>()?;
+                    if child.class_name() == "canvas-container" {
+                        continue;
+                    }
+                    let height = child.offset_height() as u32;
+                    if height > 0 {
+                        available_height = available_height.saturating_sub(height + gap);
+                    }
+                }
+            }
+        }
 
         self.cell_size = cmp::min(
             available_width / board.width,
@@ -397,6 +459,31 @@ pub async fn intro_animation() -> Result<(), JsValue> {
     Ok(())
 }
 
+/// Fades the game out, then the menu in.
+pub async fn game_to_menu_transition() -> Result<(), JsValue> {
+    fade_out_game()?;
+    JsFuture::from(sleep(500)).await?;
+    fade_in_menu()?;
+
+    Ok(())
+}
+
+/// Shows the menu's "Continue" button only when there's a game to continue.
+pub fn set_continue_visible(visible: bool) -> Result<(), JsValue> {
+    let window = web_sys::window().expect("no global `window` exists");
+    let document = window.document().expect("should have a document on window");
+
+    let continue_button = document
+        .query_selector("#continue-button")?
+        .expect("Expected `#continue-button` element");
+
+    continue_button
+        .class_list()
+        .toggle_with_force("hidden", !visible)?;
+
+    Ok(())
+}
+
 pub fn fade_in_menu() -> Result<(), JsValue> {
     let window = web_sys::window().expect("no global `window` exists");
     let document = window.document().expect("should have a document on window");
@@ -460,3 +547,230 @@ pub fn fade_out_game() -> Result<(), JsValue> {
 
     Ok(())
 }
+
+/// What the results screen shows for a finished game.
+pub struct Results<'a> {
+    /// The heading, e.g. "GAME OVER".
+    pub title: &'a str,
+    pub mode: GameMode,
+    pub stats: &'a Stats,
+    pub time_ms: f64,
+    pub pieces_per_second: f64,
+    pub finesse_faults: u32,
+    /// The mode's leaderboard, best first.
+    pub high_scores: &'a [HighScore],
+    /// Where this game placed on the leaderboard, if it made it.
+    pub rank: Option<usize>,
+}
+
+/// Shows the final stats next to the mode's high scores.
+pub fn show_results(results: &Results) -> Result<(), JsValue> {
+    let window = web_sys::window().expect("no global `window` exists");
+    let document = window.document().expect("should have a document on window");
+    let Results {
+        title,
+        mode,
+        stats,
+        rank,
+        high_scores,
+        ..
+    } = *results;
+
+    for (selector, value) in [
+        ("#results-title", title.to_string()),
+        ("#results-mode", mode.label().to_string()),
+        ("#results-score", stats.score.to_string()),
+        ("#results-lines", stats.lines.to_string()),
+        ("#results-level", stats.level.to_string()),
+        ("#results-pieces", stats.pieces.to_string()),
+        ("#results-time", format_time(results.time_ms)),
+        ("#results-pps", format!("{:.2}", results.pieces_per_second)),
+        ("#results-finesse", results.finesse_faults.to_string()),
+    ] {
+        document
+            .query_selector(selector)?
+            .expect("Expected results element")
+            .dyn_into::<HtmlElement>()?
+            .set_inner_text(value.as_str());
+    }
+
+    let best = match (rank, high_scores.first()) {
+        (Some(0), _) => "NEW PERSONAL BEST!".to_string(),
+        (_, Some(best)) if mode.ranks_by_time() => {
+            format!("BEST: {}", format_time(best.time_ms))
+        }
+        (_, Some(best)) => format!("BEST: {}", best.score),
+        (_, None) => String::new(),
+    };
+    let results_best = document
+        .query_selector("#results-best")?
+        .expect("Expected `#results-best` element")
+        .dyn_into::<HtmlElement>()?;
+    results_best.set_inner_text(&best);
+    results_best
+        .class_list()
+        .toggle_with_force("personal-best", rank == Some(0))?;
+
+    let leaderboard = document
+        .query_selector(".results-leaderboard")?
+        .expect("Expected `.results-leaderboard` element");
+    leaderboard.set_inner_html("");
+    for (index, high_score) in high_scores.iter().enumerate() {
+        let entry = document.create_element("li")?;
+        let result = if mode.ranks_by_time() {
+            format!("{:>9}", format_time(high_score.time_ms))
+        } else {
+            format!("{:>7}  {:>3}L", high_score.score, high_score.lines)
+        };
+        entry.set_text_content(Some(&format!(
+            "{}  {}",
+            result,
+            high_score.name.as_deref().unwrap_or("-")
+        )));
+        if rank == Some(index) {
+            entry.class_list().set_value("highlight");
+        }
+        leaderboard.append_child(&entry)?;
+    }
+
+    let results_container = document
+        .query_selector(".results")
+        .expect("Expected `.results` element")
+        .unwrap()
+        .dyn_into::<HtmlElement>()
+        .unwrap();
+
+    results_container.class_list().set_value("results fade-in");
+
+    Ok(())
+}
+
+/// Formats a duration as `m:ss.mmm`.
+pub fn format_time(time_ms: f64) -> String {
+    let total_ms = time_ms.max(0.0).round() as u64;
+    format!(
+        "{}:{:02}.{:03}",
+        total_ms / 60_000,
+        total_ms / 1000 % 60,
+        total_ms % 1000
+    )
+}
+
+pub fn hide_results() -> Result<(), JsValue> {
+    let window = web_sys::window().expect("no global `window` exists");
+    let document = window.document().expect("should have a document on window");
+
+    let results_container = document
+        .query_selector(".results")
+        .expect("Expected `.results` element")
+        .unwrap()
+        .dyn_into::<HtmlElement>()
+        .unwrap();
+
+    results_container.class_list().set_value("results fade-out");
+
+    Ok(())
+}
+
+pub fn show_pause_menu() -> Result<(), JsValue> {
+    let window = web_sys::window().expect("no global `window` exists");
+    let document = window.document().expect("should have a document on window");
+
+    let pause_menu = document
+        .query_selector(".pause-menu")
+        .expect("Expected `.pause-menu` element")
+        .unwrap()
+        .dyn_into::<HtmlElement>()
+        .unwrap();
+
+    pause_menu.class_list().set_value("pause-menu fade-in");
+
+    Ok(())
+}
+
+pub fn hide_pause_menu() -> Result<(), JsValue> {
+    let window = web_sys::window().expect("no global `window` exists");
+    let document = window.document().expect("should have a document on window");
+
+    let pause_menu = document
+        .query_selector(".pause-menu")
+        .expect("Expected `.pause-menu` element")
+        .unwrap()
+        .dyn_into::<HtmlElement>()
+        .unwrap();
+
+    pause_menu.class_list().set_value("pause-menu fade-out");
+
+    Ok(())
+}
+
+pub fn show_replay_controls() -> Result<(), JsValue> {
+    let window = web_sys::window().expect("no global `window` exists");
+    let document = window.document().expect("should have a document on window");
+
+    let replay_controls = document
+        .query_selector(".replay-controls")
+        .expect("Expected `.replay-controls` element")
+        .unwrap()
+        .dyn_into::<HtmlElement>()
+        .unwrap();
+
+    replay_controls
+        .class_list()
+        .set_value("replay-controls fade-in");
+
+    Ok(())
+}
+
+pub fn hide_replay_controls() -> Result<(), JsValue> {
+    let window = web_sys::window().expect("no global `window` exists");
+    let document = window.document().expect("should have a document on window");
+
+    let replay_controls = document
+        .query_selector(".replay-controls")
+        .expect("Expected `.replay-controls` element")
+        .unwrap()
+        .dyn_into::<HtmlElement>()
+        .unwrap();
+
+    replay_controls
+        .class_list()
+        .set_value("replay-controls fade-out");
+
+    Ok(())
+}
+
+/// Rebuilds the keybinds panel from `key_bindings`, so it always shows the
+/// real controls. The action in `rebinding` is shown waiting for a key.
+pub fn render_keybinds(
+    key_bindings: &KeyBindings,
+    rebinding: Option<Action>,
+) -> Result<(), JsValue> {
+    let window = web_sys::window().expect("no global `window` exists");
+    let document = window.document().expect("should have a document on window");
+
+    let keybinds_list = document
+        .query_selector(".keybinds-list")?
+        .expect("Expected `.keybinds-list` element");
+    keybinds_list.set_inner_html("");
+
+    for action in Action::ALL.iter().copied() {
+        let keys = if rebinding == Some(action) {
+            "PRESS A KEY".to_string()
+        } else {
+            key_bindings
+                .keys_for(action)
+                .map(keybinds::key_label)
+                .collect::<Vec<_>>()
+                .join(" | ")
+        };
+
+        let keybind = document.create_element("span")?.dyn_into::<HtmlElement>()?;
+        keybind.set_class_name("keybind");
+        keybind.set_attribute("data-action", &(action as u32).to_string())?;
+        keybind.set_inner_text(&format!("{} - [{}]", action.label(), keys));
+        keybinds_list.append_child(&keybind)?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,795 @@
+use std::collections::VecDeque;
+
+use wasm_bindgen::prelude::wasm_bindgen;
+
+use crate::{
+    board::Board,
+    garbage::HoleGenerator,
+    pieces::{PieceState, PieceType},
+    random::{Randomizer, RandomizerKind, Rng},
+    rotation::{RotationSystem, RotationSystemKind},
+    scoring::{self, ClearEvent, Scoring},
+};
+
+/// Running totals for a game, handed to the frontend when it ends.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Stats {
+    pub score: u32,
+    pub lines: u32,
+    pub level: u32,
+    pub pieces: u32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TopOut {
+    /// The next piece spawned overlapping the stack.
+    BlockOut,
+    /// A piece locked with any of its blocks above the visible field.
+    LockOut,
+    /// Rising garbage pushed the stack above the visible field.
+    PushOut,
+}
+
+/// Tunable timings that aren't part of the piece set or rotation system.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Rules {
+    /// How long a grounded piece waits before locking.
+    pub lock_delay_ms: f64,
+    /// How many shifts or rotations on the ground may restart the lock
+    /// delay before the piece is forced to lock.
+    pub max_lock_resets: u32,
+    /// The level a new game starts on. Every 10 lines adds one.
+    pub start_level: u32,
+    /// How many upcoming pieces are shown, between 1 and
+    /// `MAX_PREVIEW_COUNT`.
+    pub preview_count: usize,
+    /// Clearing this many lines finishes the game, as in Sprint. `None`
+    /// plays until topping out.
+    pub line_goal: Option<u32>,
+    /// The level stops rising here, as in Marathon. `None` keeps speeding
+    /// up to 20G.
+    pub max_level: Option<u32>,
+    /// Rows of garbage the board starts with, as in Dig. Clearing every
+    /// garbage row finishes the game.
+    pub garbage_rows: u32,
+    /// Another garbage row rises this often while any garbage is left.
+    /// `None` only uses the starting rows.
+    pub garbage_interval_ms: Option<f64>,
+    /// The game ends once this much time has been ticked, as in Ultra.
+    pub time_limit_ms: Option<f64>,
+}
+
+pub const MAX_PREVIEW_COUNT: usize = 6;
+
+/// The longest lock delay a replay or save may ask for.
+pub const MAX_LOCK_DELAY_MS: f64 = 60_000.0;
+
+impl Rules {
+    /// Checks rules read from a replay or save, so a corrupt or hand-made
+    /// file can't build an engine that stalls or misbehaves.
+    pub fn validate(&self) -> Result<(), String> {
+        if !(1..=MAX_PREVIEW_COUNT).contains(&self.preview_count) {
+            return Err(format!(
+                "Preview count {} is out of range",
+                self.preview_count
+            ));
+        }
+        if !(0.0..=MAX_LOCK_DELAY_MS).contains(&self.lock_delay_ms) {
+            return Err(format!("Lock delay {} is out of range", self.lock_delay_ms));
+        }
+        if self.start_level > MAX_GRAVITY_LEVEL {
+            return Err(format!("Start level {} is out of range", self.start_level));
+        }
+        if let Some(max_level) = self.max_level {
+            if max_level < self.start_level {
+                return Err(format!(
+                    "Level cap {} is below start level {}",
+                    max_level, self.start_level
+                ));
+            }
+        }
+        if self.garbage_rows > Board::new().height {
+            return Err(format!(
+                "{} garbage rows don't fit the board",
+                self.garbage_rows
+            ));
+        }
+        for (name, value) in [
+            ("Garbage interval", self.garbage_interval_ms),
+            ("Time limit", self.time_limit_ms),
+        ] {
+            if let Some(value) = value {
+                if !value.is_finite() || value <= 0.0 {
+                    return Err(format!("{} {} is out of range", name, value));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Rules {
+            lock_delay_ms: 500.0,
+            max_lock_resets: 15,
+            start_level: 1,
+            preview_count: 5,
+            line_goal: None,
+            max_level: None,
+            garbage_rows: 0,
+            garbage_interval_ms: None,
+            time_limit_ms: None,
+        }
+    }
+}
+
+/// The level at which pieces fall to the floor within a single frame.
+pub const MAX_GRAVITY_LEVEL: u32 = 20;
+
+/// Time per row of gravity on `level`, following the guideline curve
+/// `(0.8 - (level - 1) * 0.007) ^ (level - 1)` seconds. Returns `0.0` for
+/// 20G, where a piece drops to the stack as soon as it spawns.
+pub fn drop_interval_ms(level: u32) -> f64 {
+    if level >= MAX_GRAVITY_LEVEL {
+        return 0.0;
+    }
+
+    let level = level.max(1) as f64 - 1.0;
+    (0.8 - level * 0.007).powf(level) * 1000.0
+}
+
+/// The DOM-free rules of the game: board, piece queue, hold, scoring and
+/// gravity. `Game` wraps this for the browser; everything else (tests, bots,
+/// native tools) can drive it directly.
+pub struct Engine {
+    pub(crate) board: Board,
+    cursor_x: i8,
+    stats: Stats,
+    top_out: Option<TopOut>,
+    held_piece: Option<PieceType>,
+    can_hold_this_turn: bool,
+    next_queue: VecDeque<PieceType>,
+    time_since_last_drop: f64,
+    drop_interval_ms: f64,
+    rules: Rules,
+    lock_timer: f64,
+    lock_resets: u32,
+    lowest_row: i8,
+    /// The kick index of the last successful move, if it was a rotation.
+    last_kick: Option<usize>,
+    scoring: Scoring,
+    clear_events: Vec<ClearEvent>,
+    seed: u32,
+    rng: Rng,
+    pieces_drawn: u64,
+    randomizer: Box<dyn Randomizer>,
+    rotation_system: Box<dyn RotationSystem>,
+    last_locked_piece: Option<PieceState>,
+    holes: HoleGenerator,
+    garbage_drawn: u64,
+    garbage_timer: f64,
+    /// Time ticked since the game started.
+    elapsed_ms: f64,
+}
+
+/// More pieces or garbage rows than any real game draws. Restoring a
+/// snapshot replays every draw, so larger counts are rejected rather than
+/// left to stall the page.
+pub const MAX_RESTORED_DRAWS: u64 = 1 << 20;
+
+/// A plain copy of an engine's state, enough to carry the game on later.
+/// The randomizer is rebuilt from `seed` by drawing `pieces_drawn` pieces,
+/// and the garbage holes by drawing `garbage_drawn` holes.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EngineSnapshot {
+    pub seed: u32,
+    pub randomizer: RandomizerKind,
+    pub rotation_system: RotationSystemKind,
+    pub pieces_drawn: u64,
+    pub rules: Rules,
+    pub stats: Stats,
+    /// The colour of every placed block, row by row from the top.
+    pub cells: Vec<Vec<Option<String>>>,
+    pub piece_type: PieceType,
+    pub piece_row: i8,
+    pub piece_col: i8,
+    pub piece_rotation: u8,
+    pub held_piece: Option<PieceType>,
+    pub can_hold: bool,
+    pub next_queue: Vec<PieceType>,
+    pub time_since_last_drop: f64,
+    pub lock_timer: f64,
+    pub lock_resets: u32,
+    pub lowest_row: i8,
+    pub last_kick: Option<usize>,
+    pub back_to_back: bool,
+    pub combo: Option<u32>,
+    pub garbage_drawn: u64,
+    pub garbage_timer: f64,
+    pub elapsed_ms: f64,
+}
+
+impl Engine {
+    /// Two engines with the same `seed` and randomizer deal the same pieces.
+    pub fn new(
+        seed: u32,
+        randomizer: Box<dyn Randomizer>,
+        rotation_system: Box<dyn RotationSystem>,
+    ) -> Engine {
+        let mut engine = Engine {
+            board: Board::new(),
+            cursor_x: 3,
+            stats: Stats::default(),
+            top_out: None,
+            held_piece: None,
+            can_hold_this_turn: true,
+            next_queue: VecDeque::new(),
+            time_since_last_drop: 0.0,
+            drop_interval_ms: drop_interval_ms(1),
+            rules: Rules::default(),
+            lock_timer: 0.0,
+            lock_resets: 0,
+            lowest_row: 0,
+            last_kick: None,
+            scoring: Scoring::new(),
+            clear_events: Vec::new(),
+            seed,
+            rng: Rng::new(seed),
+            pieces_drawn: 0,
+            randomizer,
+            rotation_system,
+            last_locked_piece: None,
+            holes: HoleGenerator::new(seed),
+            garbage_drawn: 0,
+            garbage_timer: 0.0,
+            elapsed_ms: 0.0,
+        };
+
+        engine.update_level();
+        engine.spawn_next_piece();
+        engine
+    }
+
+    /// Starts a game with `rules` in place from the first piece, including
+    /// any starting garbage.
+    pub fn with_rules(
+        seed: u32,
+        randomizer: Box<dyn Randomizer>,
+        rotation_system: Box<dyn RotationSystem>,
+        rules: Rules,
+    ) -> Engine {
+        let mut engine = Engine::new(seed, randomizer, rotation_system);
+        engine.set_rules(rules);
+        let garbage_rows = rules.garbage_rows.min(engine.board.height);
+        engine.add_garbage(garbage_rows);
+        engine
+    }
+
+    pub fn seed(&self) -> u32 {
+        self.seed
+    }
+
+    pub fn snapshot(&self) -> EngineSnapshot {
+        let piece = &self.board.current_piece;
+        EngineSnapshot {
+            seed: self.seed,
+            randomizer: self.randomizer.kind(),
+            rotation_system: self.rotation_system.kind(),
+            pieces_drawn: self.pieces_drawn,
+            rules: self.rules,
+            stats: self.stats,
+            cells: self.board.placed_pieces.clone(),
+            piece_type: piece.piece_type.clone(),
+            piece_row: piece.row,
+            piece_col: piece.col,
+            piece_rotation: piece.rotation,
+            held_piece: self.held_piece.clone(),
+            can_hold: self.can_hold_this_turn,
+            next_queue: self.next_queue.iter().cloned().collect(),
+            time_since_last_drop: self.time_since_last_drop,
+            lock_timer: self.lock_timer,
+            lock_resets: self.lock_resets,
+            lowest_row: self.lowest_row,
+            last_kick: self.last_kick,
+            back_to_back: self.scoring.back_to_back(),
+            combo: self.scoring.combo(),
+            garbage_drawn: self.garbage_drawn,
+            garbage_timer: self.garbage_timer,
+            elapsed_ms: self.elapsed_ms,
+        }
+    }
+
+    /// Rebuilds an engine from `snapshot`, checking that it describes a
+    /// game that could actually be in progress.
+    pub fn restore(snapshot: &EngineSnapshot) -> Result<Engine, String> {
+        // Every piece drawn has locked, is in play, is held or is queued.
+        let pieces_dealt = u64::from(snapshot.stats.pieces)
+            + 1
+            + u64::from(snapshot.held_piece.is_some())
+            + snapshot.next_queue.len() as u64;
+        if snapshot.pieces_drawn != pieces_dealt || snapshot.pieces_drawn > MAX_RESTORED_DRAWS {
+            return Err("Saved piece count doesn't match the game".to_string());
+        }
+        if snapshot.garbage_drawn > MAX_RESTORED_DRAWS {
+            return Err("Saved garbage count is out of range".to_string());
+        }
+
+        let mut rng = Rng::new(snapshot.seed);
+        let mut randomizer = snapshot.randomizer.build();
+        for _ in 0..snapshot.pieces_drawn {
+            randomizer.next(&mut rng);
+        }
+
+        let mut board = Board::new();
+        let mut holes = HoleGenerator::new(snapshot.seed);
+        for _ in 0..snapshot.garbage_drawn {
+            holes.next_hole(board.width as usize);
+        }
+
+        if snapshot.cells.len() != board.height as usize
+            || snapshot
+                .cells
+                .iter()
+                .any(|row| row.len() != board.width as usize)
+        {
+            return Err("Saved board has the wrong size".to_string());
+        }
+        board.placed_pieces = snapshot.cells.clone();
+
+        let rotation_system = snapshot.rotation_system.build();
+        let piece = rotation_system.piece(&snapshot.piece_type);
+        if usize::from(snapshot.piece_rotation) >= piece.rotations.len() {
+            return Err("Saved piece has an unknown rotation".to_string());
+        }
+        let mut current_piece =
+            PieceState::new(snapshot.piece_type.clone(), piece, snapshot.piece_col);
+        current_piece.row = snapshot.piece_row;
+        current_piece.rotation = snapshot.piece_rotation;
+        if !board.is_valid_position(&current_piece) {
+            return Err("Saved piece overlaps the stack".to_string());
+        }
+        board.current_piece = current_piece;
+
+        if snapshot.next_queue.is_empty() {
+            return Err("Saved queue is empty".to_string());
+        }
+
+        let mut engine = Engine {
+            board,
+            cursor_x: 3,
+            stats: snapshot.stats,
+            top_out: None,
+            held_piece: snapshot.held_piece.clone(),
+            can_hold_this_turn: snapshot.can_hold,
+            next_queue: snapshot.next_queue.iter().cloned().collect(),
+            time_since_last_drop: snapshot.time_since_last_drop,
+            drop_interval_ms: drop_interval_ms(snapshot.stats.level),
+            rules: snapshot.rules,
+            lock_timer: snapshot.lock_timer,
+            lock_resets: snapshot.lock_resets,
+            lowest_row: snapshot.lowest_row,
+            last_kick: snapshot.last_kick,
+            scoring: Scoring::resume(snapshot.back_to_back, snapshot.combo),
+            clear_events: Vec::new(),
+            seed: snapshot.seed,
+            rng,
+            pieces_drawn: snapshot.pieces_drawn,
+            randomizer,
+            rotation_system,
+            last_locked_piece: None,
+            holes,
+            garbage_drawn: snapshot.garbage_drawn,
+            garbage_timer: snapshot.garbage_timer,
+            elapsed_ms: snapshot.elapsed_ms,
+        };
+        engine.fill_next_queue();
+        Ok(engine)
+    }
+
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+
+    pub fn current_piece(&self) -> &PieceState {
+        &self.board.current_piece
+    }
+
+    pub fn score(&self) -> u32 {
+        self.stats.score
+    }
+
+    pub fn stats(&self) -> Stats {
+        self.stats
+    }
+
+    pub fn top_out(&self) -> Option<TopOut> {
+        self.top_out
+    }
+
+    /// Whether the game has ended, by topping out, by reaching the goal or
+    /// by running out of time.
+    pub fn is_game_over(&self) -> bool {
+        self.top_out.is_some() || self.goal_reached() || self.is_time_up()
+    }
+
+    pub fn is_time_up(&self) -> bool {
+        self.rules
+            .time_limit_ms
+            .is_some_and(|time_limit_ms| self.elapsed_ms >= time_limit_ms)
+    }
+
+    /// Time ticked since the game started.
+    pub fn elapsed_ms(&self) -> f64 {
+        self.elapsed_ms
+    }
+
+    pub fn goal_reached(&self) -> bool {
+        let lines_done = self
+            .rules
+            .line_goal
+            .is_some_and(|line_goal| self.stats.lines >= line_goal);
+        let dug_out = self.rules.garbage_rows > 0 && self.board.garbage_rows() == 0;
+        lines_done || dug_out
+    }
+
+    /// The piece that locked most recently, where it locked.
+    pub fn last_locked_piece(&self) -> Option<&PieceState> {
+        self.last_locked_piece.as_ref()
+    }
+
+    pub fn held_piece(&self) -> Option<&PieceType> {
+        self.held_piece.as_ref()
+    }
+
+    pub fn next_piece(&self) -> &PieceType {
+        &self.next_queue[0]
+    }
+
+    /// The upcoming pieces shown in the preview, soonest first.
+    pub fn next_pieces(&self) -> impl Iterator<Item = &PieceType> {
+        self.next_queue.iter().take(self.rules.preview_count)
+    }
+
+    pub fn rules(&self) -> Rules {
+        self.rules
+    }
+
+    pub fn set_rules(&mut self, rules: Rules) {
+        self.rules = Rules {
+            preview_count: rules.preview_count.clamp(1, MAX_PREVIEW_COUNT),
+            ..rules
+        };
+        self.update_level();
+        self.fill_next_queue();
+    }
+
+    pub fn level(&self) -> u32 {
+        self.stats.level
+    }
+
+    pub fn drop_interval_ms(&self) -> f64 {
+        self.drop_interval_ms
+    }
+
+    pub fn randomizer(&self) -> &dyn Randomizer {
+        self.randomizer.as_ref()
+    }
+
+    pub fn rotation_system(&self) -> &dyn RotationSystem {
+        self.rotation_system.as_ref()
+    }
+
+    /// A piece of `piece_type` in its spawn state, for drawing previews.
+    pub fn preview_piece(&self, piece_type: &PieceType) -> PieceState {
+        PieceState::new(
+            piece_type.clone(),
+            self.rotation_system.piece(piece_type),
+            0,
+        )
+    }
+
+    pub fn ghost_piece(&self) -> PieceState {
+        let mut ghost_piece = self.board.current_piece.clone();
+        while self.board.is_valid_position(&ghost_piece.move_down()) {
+            ghost_piece.row += 1;
+        }
+        ghost_piece
+    }
+
+    pub fn tick(&mut self, delta_time: f64) {
+        if self.is_game_over() {
+            return;
+        }
+
+        self.elapsed_ms += delta_time;
+        self.time_since_last_drop += delta_time;
+
+        while self.time_since_last_drop >= self.drop_interval_ms {
+            self.time_since_last_drop -= self.drop_interval_ms;
+            if !self.move_down() {
+                self.time_since_last_drop = 0.0;
+                break;
+            }
+        }
+
+        // Time may have run out this tick; the piece mustn't lock after that.
+        if self.is_grounded() && !self.is_game_over() {
+            self.lock_timer += delta_time;
+            if self.lock_timer >= self.rules.lock_delay_ms
+                || self.lock_resets >= self.rules.max_lock_resets
+            {
+                self.lock_current_piece();
+            }
+        }
+
+        if let Some(garbage_interval_ms) = self.rules.garbage_interval_ms {
+            if self.rules.garbage_rows > 0 && !self.is_game_over() {
+                self.garbage_timer += delta_time;
+                while self.garbage_timer >= garbage_interval_ms && self.top_out.is_none() {
+                    self.garbage_timer -= garbage_interval_ms;
+                    self.add_garbage(1);
+                }
+            }
+        }
+    }
+
+    pub fn move_left(&mut self) -> bool {
+        self.try_shift(-1)
+    }
+
+    pub fn move_right(&mut self) -> bool {
+        self.try_shift(1)
+    }
+
+    pub fn rotate_clockwise(&mut self) -> bool {
+        self.try_rotate(true)
+    }
+
+    pub fn rotate_counter_clockwise(&mut self) -> bool {
+        self.try_rotate(false)
+    }
+
+    pub fn rotate_180(&mut self) -> bool {
+        if self.is_game_over() {
+            return false;
+        }
+
+        let current_piece = &self.board.current_piece;
+        let kicks = self
+            .rotation_system
+            .kicks_180(&current_piece.piece_type, current_piece.rotation);
+
+        let mut next_piece = current_piece.clone();
+        next_piece.rotate();
+        next_piece.rotate();
+        self.try_kicks(next_piece, kicks)
+    }
+
+    pub fn soft_drop(&mut self) -> bool {
+        if !self.move_down() {
+            return false;
+        }
+        self.stats.score += scoring::SOFT_DROP_POINTS_PER_CELL;
+        true
+    }
+
+    pub fn hard_drop(&mut self) {
+        if self.is_game_over() {
+            return;
+        }
+
+        while self.move_down() {
+            self.stats.score += scoring::HARD_DROP_POINTS_PER_CELL;
+        }
+        self.lock_current_piece();
+    }
+
+    /// Drains the clears reported since the last call, oldest first.
+    pub fn take_clear_events(&mut self) -> Vec<ClearEvent> {
+        std::mem::take(&mut self.clear_events)
+    }
+
+    /// Whether the current piece is resting on the stack or the floor.
+    pub fn is_grounded(&self) -> bool {
+        !self
+            .board
+            .is_valid_position(&self.board.current_piece.move_down())
+    }
+
+    pub fn hold(&mut self) {
+        if self.is_game_over() || !self.can_hold_this_turn {
+            return;
+        }
+
+        self.can_hold_this_turn = false;
+
+        let current_piece_type = self.board.current_piece.piece_type.clone();
+
+        if let Some(held) = self.held_piece.take() {
+            self.spawn(held);
+        } else {
+            self.spawn_next_piece();
+        }
+        self.held_piece = Some(current_piece_type);
+    }
+}
+
+impl Engine {
+    fn try_shift(&mut self, col_offset: i8) -> bool {
+        if self.is_game_over() {
+            return false;
+        }
+
+        let mut next_piece = self.board.current_piece.clone();
+        next_piece.col += col_offset;
+        if self.board.is_valid_position(&next_piece) {
+            self.apply_move(next_piece, None);
+            return true;
+        }
+        false
+    }
+
+    fn try_rotate(&mut self, clockwise: bool) -> bool {
+        if self.is_game_over() {
+            return false;
+        }
+
+        let current_piece = &self.board.current_piece;
+        let kicks = self.rotation_system.kicks(
+            &current_piece.piece_type,
+            current_piece.rotation,
+            clockwise,
+        );
+
+        let mut next_piece = current_piece.clone();
+        if clockwise {
+            next_piece.rotate();
+        } else {
+            next_piece.rotate_counter_clockwise();
+        }
+
+        self.try_kicks(next_piece, kicks)
+    }
+
+    /// Places `next_piece` at the first of `kicks` that fits.
+    fn try_kicks(&mut self, next_piece: PieceState, kicks: &[(i8, i8)]) -> bool {
+        if !self.board.is_valid_position(&next_piece)
+            && !self.rotation_system.can_kick(&self.board, &next_piece)
+        {
+            return false;
+        }
+
+        for (kick, (col_offset, row_offset)) in kicks.iter().enumerate() {
+            let mut kicked_piece = next_piece.clone();
+            kicked_piece.col += col_offset;
+            kicked_piece.row += row_offset;
+
+            if self.board.is_valid_position(&kicked_piece) {
+                self.apply_move(kicked_piece, Some(kick));
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Applies a successful shift or rotation. Moving a grounded piece spends
+    /// one of its lock resets and restarts the lock delay.
+    fn apply_move(&mut self, piece: PieceState, kick: Option<usize>) {
+        let was_grounded = self.is_grounded();
+        self.board.current_piece = piece;
+        self.last_kick = kick;
+
+        if was_grounded && self.lock_resets < self.rules.max_lock_resets {
+            self.lock_resets += 1;
+            self.lock_timer = 0.0;
+        }
+    }
+
+    /// Moves the current piece down one row. Returns `true` if it moved;
+    /// locking is left to the lock delay in `tick`.
+    fn move_down(&mut self) -> bool {
+        if self.is_game_over() {
+            return false;
+        }
+
+        let next_piece = self.board.current_piece.move_down();
+        if !self.board.is_valid_position(&next_piece) {
+            return false;
+        }
+
+        self.board.current_piece = next_piece;
+        self.last_kick = None;
+        self.lock_timer = 0.0;
+        if self.board.current_piece.row > self.lowest_row {
+            self.lowest_row = self.board.current_piece.row;
+            self.lock_resets = 0;
+        }
+        true
+    }
+
+    fn lock_current_piece(&mut self) {
+        if self.board.current_piece.iter_blocks().any(|(r, _)| r < 0) {
+            self.top_out = Some(TopOut::LockOut);
+            return;
+        }
+
+        let spin = scoring::detect_t_spin(&self.board, &self.board.current_piece, self.last_kick);
+
+        self.last_locked_piece = Some(self.board.current_piece.clone());
+        self.board.lock_piece();
+        self.stats.pieces += 1;
+        let lines_cleared = self.board.clear_lines();
+
+        if let Some(event) =
+            self.scoring
+                .score_lock(lines_cleared, spin, self.board.is_empty(), self.stats.level)
+        {
+            self.stats.score += event.points;
+            self.clear_events.push(event);
+        }
+
+        self.stats.lines += lines_cleared;
+        self.update_level();
+        if self.goal_reached() {
+            return;
+        }
+
+        self.spawn_next_piece();
+        self.can_hold_this_turn = true;
+    }
+
+    /// Raises `rows` rows of garbage under the stack, lifting the current
+    /// piece clear of it.
+    fn add_garbage(&mut self, rows: u32) {
+        let width = self.board.width as usize;
+        let holes: Vec<usize> = (0..rows).map(|_| self.holes.next_hole(width)).collect();
+        self.garbage_drawn += u64::from(rows);
+
+        if !self.board.insert_garbage_rows(&holes) {
+            self.top_out = Some(TopOut::PushOut);
+        }
+        while !self.board.is_valid_position(&self.board.current_piece) {
+            self.board.current_piece.row -= 1;
+            self.lowest_row -= 1;
+        }
+    }
+
+    fn update_level(&mut self) {
+        self.stats.level = self.rules.start_level + self.stats.lines / 10;
+        if let Some(max_level) = self.rules.max_level {
+            self.stats.level = self.stats.level.min(max_level);
+        }
+        self.drop_interval_ms = drop_interval_ms(self.stats.level);
+    }
+
+    fn spawn_next_piece(&mut self) {
+        self.fill_next_queue();
+        let piece_type = self.next_queue.pop_front().expect("queue was just filled");
+        self.fill_next_queue();
+        self.spawn(piece_type);
+    }
+
+    /// Tops up the queue from the randomizer. Pieces already queued are never
+    /// dropped, so changing the preview length doesn't change the sequence.
+    fn fill_next_queue(&mut self) {
+        while self.next_queue.len() < self.rules.preview_count.max(1) {
+            let piece_type = self.randomizer.next(&mut self.rng);
+            self.pieces_drawn += 1;
+            self.next_queue.push_back(piece_type);
+        }
+    }
+
+    fn spawn(&mut self, piece_type: PieceType) {
+        let piece = self.rotation_system.piece(&piece_type);
+        self.board.current_piece = PieceState::new(piece_type, piece, self.cursor_x);
+        self.lock_timer = 0.0;
+        self.lock_resets = 0;
+        self.lowest_row = self.board.current_piece.row;
+        self.last_kick = None;
+        if !self.board.is_valid_position(&self.board.current_piece) {
+            self.top_out = Some(TopOut::BlockOut);
+        }
+    }
+}
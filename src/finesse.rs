@@ -0,0 +1,186 @@
+use std::collections::VecDeque;
+
+use crate::{
+    board::Board,
+    engine::Engine,
+    keybinds::Action,
+    pieces::{PieceState, PieceType},
+    replay::ReplayInput,
+    rotation::RotationSystem,
+};
+
+/// The fewest inputs that take `spawn` to where `target` sits, counting a
+/// tap, a DAS to the wall and each rotation as one input. The search runs on
+/// an empty board, so placements that need a soft drop aren't judged.
+pub fn optimal_inputs(
+    rotation_system: &dyn RotationSystem,
+    spawn: &PieceState,
+    target: &PieceState,
+) -> Option<u32> {
+    let board = Board::new();
+    let goal = footprint(target);
+
+    let mut seen = vec![(spawn.col, spawn.rotation)];
+    let mut queue = VecDeque::new();
+    queue.push_back((spawn.clone(), 0));
+    while let Some((piece, inputs)) = queue.pop_front() {
+        if footprint(&piece) == goal {
+            return Some(inputs);
+        }
+        for next in moves(&board, rotation_system, &piece) {
+            if !seen.contains(&(next.col, next.rotation)) {
+                seen.push((next.col, next.rotation));
+                queue.push_back((next, inputs + 1));
+            }
+        }
+    }
+    None
+}
+
+/// The columns and shape a piece covers, ignoring height. Different
+/// rotation states of S, Z and I can cover the same cells.
+fn footprint(piece: &PieceState) -> Vec<(i8, i8)> {
+    let blocks: Vec<(i8, i8)> = piece.iter_blocks().collect();
+    let top = blocks.iter().map(|&(r, _)| r).min().unwrap_or(0);
+    let mut footprint: Vec<(i8, i8)> = blocks.iter().map(|&(r, c)| (r - top, c)).collect();
+    footprint.sort_unstable();
+    footprint
+}
+
+fn moves(
+    board: &Board,
+    rotation_system: &dyn RotationSystem,
+    piece: &PieceState,
+) -> Vec<PieceState> {
+    let mut moves = Vec::new();
+
+    for direction in [-1, 1] {
+        let mut shifted = piece.clone();
+        shifted.col += direction;
+        if !board.is_valid_position(&shifted) {
+            continue;
+        }
+        moves.push(shifted.clone());
+
+        // Holding the direction past DAS runs into the wall.
+        loop {
+            let mut next = shifted.clone();
+            next.col += direction;
+            if !board.is_valid_position(&next) {
+                break;
+            }
+            shifted = next;
+        }
+        moves.push(shifted);
+    }
+
+    let mut clockwise = piece.clone();
+    clockwise.rotate();
+    let mut counter_clockwise = piece.clone();
+    counter_clockwise.rotate_counter_clockwise();
+    let mut half_turn = clockwise.clone();
+    half_turn.rotate();
+    for (rotated, kicks) in [
+        (
+            clockwise,
+            rotation_system.kicks(&piece.piece_type, piece.rotation, true),
+        ),
+        (
+            counter_clockwise,
+            rotation_system.kicks(&piece.piece_type, piece.rotation, false),
+        ),
+        (
+            half_turn,
+            rotation_system.kicks_180(&piece.piece_type, piece.rotation),
+        ),
+    ] {
+        let kicked = kicks.iter().find_map(|&(col_offset, row_offset)| {
+            let mut kicked = rotated.clone();
+            kicked.col += col_offset;
+            kicked.row += row_offset;
+            Some(kicked).filter(|kicked| board.is_valid_position(kicked))
+        });
+        moves.extend(kicked);
+    }
+
+    moves
+}
+
+/// Counts the inputs spent on each piece and compares them with
+/// `optimal_inputs` when it locks.
+#[derive(Clone)]
+pub struct FinesseTracker {
+    faults: u32,
+    inputs: u32,
+    soft_dropped: bool,
+    pieces: u32,
+    held_piece: Option<PieceType>,
+    spawn: PieceState,
+}
+
+impl FinesseTracker {
+    pub fn new(engine: &Engine) -> FinesseTracker {
+        FinesseTracker {
+            faults: 0,
+            inputs: 0,
+            soft_dropped: false,
+            pieces: engine.stats().pieces,
+            held_piece: engine.held_piece().cloned(),
+            spawn: engine.current_piece().clone(),
+        }
+    }
+
+    /// Carries on counting from a saved game that already had `faults`.
+    pub fn resume(engine: &Engine, faults: u32) -> FinesseTracker {
+        FinesseTracker {
+            faults,
+            ..FinesseTracker::new(engine)
+        }
+    }
+
+    /// Inputs beyond the optimum over the whole game.
+    pub fn faults(&self) -> u32 {
+        self.faults
+    }
+
+    /// Call before `input` is applied to the engine.
+    pub fn before_input(&mut self, input: ReplayInput) {
+        match input {
+            ReplayInput::Press(Action::SoftDrop) => self.soft_dropped = true,
+            ReplayInput::Press(
+                Action::MoveLeft
+                | Action::MoveRight
+                | Action::RotateClockwise
+                | Action::RotateCounterClockwise
+                | Action::Rotate180,
+            ) => self.inputs += 1,
+            _ => {}
+        }
+    }
+
+    /// Call after anything that may have locked or held the piece.
+    pub fn after_update(&mut self, engine: &Engine) {
+        if engine.stats().pieces != self.pieces {
+            if let Some(locked) = engine.last_locked_piece() {
+                if !self.soft_dropped {
+                    let optimal = optimal_inputs(engine.rotation_system(), &self.spawn, locked);
+                    if let Some(optimal) = optimal {
+                        self.faults += self.inputs.saturating_sub(optimal);
+                    }
+                }
+            }
+            self.start_piece(engine);
+        } else if engine.held_piece() != self.held_piece.as_ref() {
+            // Inputs spent before holding don't count against either piece.
+            self.start_piece(engine);
+        }
+    }
+
+    fn start_piece(&mut self, engine: &Engine) {
+        self.inputs = 0;
+        self.soft_dropped = false;
+        self.pieces = engine.stats().pieces;
+        self.held_piece = engine.held_piece().cloned();
+        self.spawn = engine.current_piece().clone();
+    }
+}
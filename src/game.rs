@@ -1,55 +1,143 @@
-use wasm_bindgen::{prelude::wasm_bindgen, JsCast, JsValue};
+use std::{cell::Cell, rc::Rc};
+
+use wasm_bindgen::{
+    prelude::{wasm_bindgen, Closure},
+    JsCast, JsValue,
+};
 use web_sys::HtmlParagraphElement;
 
 use crate::{
-    board::Board,
-    display::{self, Display},
+    display::{self, Display, Results},
+    engine::{Engine, Stats},
+    finesse::FinesseTracker,
+    gamepad::{self, GamepadInput},
+    input::{Handling, InputHandler},
+    keybinds::{Action, KeyBindings},
+    leaderboard::{HighScore, Leaderboard},
     log,
-    pieces::{PieceState, PieceType},
+    mode::GameMode,
+    random::RandomizerKind,
+    replay::{self, Replay, ReplayInput, ReplayPlayer},
+    rotation::RotationSystemKind,
+    save::SavedGame,
+    scoring::ClearEvent,
+    timestep::{FixedTimestep, STEP_MS},
+    touch::{Gesture, GestureConfig, GestureRecognizer},
     utils,
 };
-use web_sys::js_sys::Math;
+use web_sys::js_sys::{Date, Function, Math};
+
+const KEY_BINDINGS_STORAGE_KEY: &str = "tetris.key_bindings";
+const SAVED_GAME_STORAGE_KEY: &str = "tetris.saved_game";
+const LEADERBOARD_STORAGE_KEY: &str = "tetris.leaderboard";
+const PLAYER_NAME_STORAGE_KEY: &str = "tetris.player_name";
+
+/// How long the last clear's label stays in the sidebar.
+const CLEAR_LABEL_DURATION_MS: f64 = 2000.0;
+
+/// The longest step a single frame may advance the game by. Browsers stop
+/// calling `requestAnimationFrame` in background tabs, and the first frame
+/// back would otherwise apply all of that time as gravity at once.
+const MAX_FRAME_DELTA_MS: f64 = 100.0;
+
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GameState {
+    Menu,
+    Running,
+    Paused,
+    GameOver,
+    /// Watching a recording of the last game.
+    Replay,
+}
 
 #[wasm_bindgen]
 pub struct Game {
-    board: Board,
+    engine: Engine,
+    input: InputHandler,
+    key_bindings: KeyBindings,
+    rebinding: Option<Action>,
+    gamepad: GamepadInput,
+    gestures: GestureRecognizer,
     display: Display,
-    time_since_last_drop: f64,
-    cursor_x: i8,
-    score: u32,
-    held_piece: Option<PieceType>,
-    can_hold_this_turn: bool,
-    next_piece: PieceType,
-    bag: Vec<PieceType>,
-    bag_index: usize,
-    drop_interval_ms: i32,
-    is_game_running: bool,
+    state: GameState,
+    mode: GameMode,
+    /// Where the last finished game placed on the leaderboard.
+    last_rank: Option<usize>,
+    on_game_over: Option<Function>,
+    clear_label: String,
+    clear_label_ms: f64,
+    timestep: FixedTimestep,
+    /// Real time spent running the current game, for timed modes.
+    play_time_ms: f64,
+    finesse: FinesseTracker,
+    recording: Replay,
+    playback: Option<ReplayPlayer>,
+    /// Where to go when the replay is closed.
+    playback_origin: GameState,
+    focus_watcher: Option<FocusWatcher>,
 }
 
 #[wasm_bindgen]
 impl Game {
     #[wasm_bindgen(constructor)]
     pub fn new() -> Result<Game, JsValue> {
+        Game::new_with_options(None, RandomizerKind::SevenBag, RotationSystemKind::Srs)
+    }
+
+    pub fn new_with_seed(seed: u32) -> Result<Game, JsValue> {
+        Game::new_with_options(
+            Some(seed),
+            RandomizerKind::SevenBag,
+            RotationSystemKind::Srs,
+        )
+    }
+
+    pub fn new_with_rotation_system(rotation_system: RotationSystemKind) -> Result<Game, JsValue> {
+        Game::new_with_options(None, RandomizerKind::SevenBag, rotation_system)
+    }
+
+    /// Picks a random seed when `seed` is `None`.
+    pub fn new_with_options(
+        seed: Option<u32>,
+        randomizer: RandomizerKind,
+        rotation_system: RotationSystemKind,
+    ) -> Result<Game, JsValue> {
         utils::set_panic_hook();
+        let seed = seed.unwrap_or_else(random_seed);
+        let engine = Engine::new(seed, randomizer.build(), rotation_system.build());
+        let input = InputHandler::new(Handling::default());
+        let recording = Replay::start(&engine, input.handling());
+        let finesse = FinesseTracker::new(&engine);
         let mut game = Game {
-            board: Board::new(),
+            engine,
+            input,
+            key_bindings: load_key_bindings(),
+            rebinding: None,
+            gamepad: GamepadInput::default(),
+            gestures: GestureRecognizer::default(),
             display: Display::new()?,
-            time_since_last_drop: 0.0,
-            cursor_x: 3,
-            score: 0,
-            held_piece: None,
-            can_hold_this_turn: true,
-            next_piece: PieceType::Straight,
-            bag: Vec::new(),
-            bag_index: 0,
-            drop_interval_ms: 1000,
-            is_game_running: false,
+            state: GameState::Menu,
+            mode: GameMode::Endless,
+            last_rank: None,
+            on_game_over: None,
+            clear_label: String::new(),
+            clear_label_ms: 0.0,
+            timestep: FixedTimestep::new(),
+            play_time_ms: 0.0,
+            finesse,
+            recording,
+            playback: None,
+            playback_origin: GameState::GameOver,
+            focus_watcher: FocusWatcher::new()
+                .map_err(|err| log(&format!("Error watching page focus: {:?}", err)))
+                .ok(),
         };
 
-        game.new_bag();
-        game.next_piece = game.get_next_piece();
-
         game.resize();
+        game.render_keybinds();
+        display::set_continue_visible(game.has_saved_game())
+            .expect("Expected `set_continue_visible` call to succeed");
         wasm_bindgen_futures::spawn_local(async move {
             display::intro_animation().await.unwrap_or_else(|err| {
                 log(&format!("Error during intro animation: {:?}", err));
@@ -58,79 +146,409 @@ impl Game {
         Ok(game)
     }
 
+    pub fn seed(&self) -> u32 {
+        self.engine.seed()
+    }
+
+    pub fn randomizer(&self) -> RandomizerKind {
+        self.engine.randomizer().kind()
+    }
+
+    /// Advances the simulation by `delta_time` in fixed 60 Hz steps. Call
+    /// `render` afterwards to draw the result.
     pub fn tick(&mut self, delta_time: f64) {
-        if !self.is_game_running {
+        self.poll_gamepad();
+
+        if let Some(focus_watcher) = &self.focus_watcher {
+            if focus_watcher.take_focus_lost() {
+                self.pause_game();
+            }
+        }
+
+        if let Some(playback) = &mut self.playback {
+            let frame = playback.frame();
+            let clear_events = playback.advance(delta_time.clamp(0.0, MAX_FRAME_DELTA_MS));
+            let elapsed_ms = (playback.frame() - frame) as f64 * STEP_MS;
+            self.show_clear_events(&clear_events, elapsed_ms);
             return;
         }
 
-        let mut ghost_piece = self.board.current_piece.clone();
-        while self.board.is_valid_position(&ghost_piece.move_down()) {
-            ghost_piece.row += 1;
+        if self.state != GameState::Running {
+            return;
+        }
+        // Inputs can end the game between frames, e.g. a hard drop clearing
+        // the last line of a sprint. Stop the clock before adding this frame.
+        if self.engine.is_game_over() {
+            self.end_game();
+            return;
         }
 
-        self.display.draw(
-            &self.board,
-            &self.held_piece,
-            &self.next_piece,
-            &ghost_piece,
-        );
+        let delta_time = delta_time.clamp(0.0, MAX_FRAME_DELTA_MS);
+        self.play_time_ms += delta_time;
+        let steps = self.timestep.advance(delta_time);
+        for _ in 0..steps {
+            self.step();
+            if self.engine.is_game_over() {
+                self.end_game();
+                return;
+            }
+        }
+    }
+
+    /// Draws the playfield and sidebar. Only a running game or a replay is
+    /// redrawn; a paused one keeps its overlay.
+    pub fn render(&self) {
+        if !matches!(self.state, GameState::Running | GameState::Replay) {
+            return;
+        }
+
+        self.draw();
 
         let window = web_sys::window().expect("no global `window` exists");
         let document = window.document().expect("should have a document on window");
 
-        let score_element = document
-            .query_selector("#score")
-            .expect("Expected `#score` element")
+        let stats = self.displayed_engine().stats();
+        for (selector, value) in [
+            ("#score", stats.score.to_string()),
+            ("#lines", stats.lines.to_string()),
+            ("#level", stats.level.to_string()),
+            ("#timer", display::format_time(self.displayed_time_ms())),
+        ] {
+            let panel_element = document
+                .query_selector(selector)
+                .expect("Expected sidebar panel element")
+                .unwrap()
+                .dyn_into::<HtmlParagraphElement>()
+                .expect("Expected cast into `HtmlParagraphElement` to succeed");
+            panel_element.set_inner_text(value.as_str());
+        }
+
+        let clear_label_element = document
+            .query_selector("#clear-label")
+            .expect("Expected `#clear-label` element")
             .unwrap()
             .dyn_into::<HtmlParagraphElement>()
             .expect("Expected cast into `HtmlParagraphElement` to succeed");
-        score_element.set_inner_text(format!("{}", self.score).as_str());
+        clear_label_element.set_inner_text(&self.clear_label);
+    }
 
-        self.time_since_last_drop += delta_time;
+    /// How far the current time is between two logic steps, from `0.0` to
+    /// `1.0`, for renderers that interpolate.
+    pub fn frame_alpha(&self) -> f64 {
+        self.timestep.alpha()
+    }
 
-        if self.time_since_last_drop >= self.drop_interval_ms.into() {
-            self.time_since_last_drop -= self.drop_interval_ms as f64;
-            let mut next_piece = self.board.current_piece.clone();
-            next_piece.row += 1;
-            if self.board.is_valid_position(&next_piece) {
-                self.board.current_piece = next_piece;
-            } else {
-                self.board.lock_piece();
-                let lines_cleared = self.board.clear_lines();
-
-                self.score += match lines_cleared {
-                    1 => 100,
-                    2 => 300,
-                    3 => 500,
-                    4 => 800,
-                    _ => 0,
-                };
-
-                self.board.current_piece = PieceState::new(self.next_piece.clone(), self.cursor_x);
-
-                self.can_hold_this_turn = true;
-                let new_piece_type = self.get_next_piece();
-                self.next_piece = new_piece_type;
-            }
-        }
+    /// The number of logic steps run in this game.
+    pub fn frame(&self) -> u64 {
+        self.timestep.frame()
+    }
+
+    /// The label of the most recent clear, such as "B2B TETRIS", or an empty
+    /// string once it has faded.
+    pub fn clear_label(&self) -> String {
+        self.clear_label.clone()
     }
 
     pub fn resize(&mut self) {
         self.display
-            .resize(&self.board)
+            .resize(self.engine.board())
             .unwrap_or_else(|err| log(&format!("Error during resize: {:?}", err)));
+        if self.state == GameState::Paused {
+            // Resizing the canvas clears it.
+            self.draw();
+        }
+        self.gestures
+            .set_config(GestureConfig::for_cell_size(self.display.cell_size() as f64));
+    }
+
+    pub fn state(&self) -> GameState {
+        self.state
     }
 
     pub fn is_game_running(&self) -> bool {
-        self.is_game_running
+        self.state == GameState::Running
     }
 
     pub fn pause_game(&mut self) {
-        self.is_game_running = false;
+        if self.state == GameState::Running {
+            self.state = GameState::Paused;
+            self.apply_input(ReplayInput::ReleaseAll);
+            self.draw();
+            display::show_pause_menu().expect("Expected `show_pause_menu` call to succeed");
+            self.save_game();
+        }
     }
 
     pub fn resume_game(&mut self) {
-        self.is_game_running = true;
+        if self.state == GameState::Paused {
+            display::hide_pause_menu().expect("Expected `hide_pause_menu` call to succeed");
+        }
+        if self.state == GameState::Menu {
+            self.start_recording();
+        }
+        if matches!(self.state, GameState::Menu | GameState::Paused) {
+            self.state = GameState::Running;
+        }
+    }
+
+    /// Throws away the current game and starts a new one straight away.
+    pub fn restart_game(&mut self) {
+        clear_saved_game();
+        self.reset();
+        self.start_recording();
+        self.state = GameState::Running;
+    }
+
+    /// Saves the current game, if there is one to continue, and fades back
+    /// to the main menu.
+    pub fn quit_to_menu(&mut self) {
+        self.save_game();
+        self.reset();
+        self.state = GameState::Menu;
+        wasm_bindgen_futures::spawn_local(async {
+            display::game_to_menu_transition()
+                .await
+                .unwrap_or_else(|err| {
+                    log(&format!("Error during menu transition: {:?}", err));
+                });
+        });
+    }
+
+    /// Stores the game in progress in `localStorage` so it survives a page
+    /// reload. Called on pause; call it on `pagehide` as well.
+    pub fn save_game(&self) {
+        if !matches!(self.state, GameState::Running | GameState::Paused)
+            || self.engine.is_game_over()
+        {
+            return;
+        }
+
+        let saved_game = SavedGame {
+            mode: self.mode,
+            engine: self.engine.snapshot(),
+            replay: self.recording.clone(),
+            frame: self.timestep.frame(),
+            finesse_faults: self.finesse.faults(),
+        };
+        if let Some(storage) = utils::local_storage() {
+            if let Err(err) = storage.set_item(SAVED_GAME_STORAGE_KEY, &saved_game.to_base64()) {
+                log(&format!("Error saving game: {:?}", err));
+                return;
+            }
+            display::set_continue_visible(true)
+                .expect("Expected `set_continue_visible` call to succeed");
+        }
+    }
+
+    pub fn has_saved_game(&self) -> bool {
+        utils::local_storage()
+            .and_then(|storage| storage.get_item(SAVED_GAME_STORAGE_KEY).ok().flatten())
+            .is_some()
+    }
+
+    /// Loads the saved game from the menu. It starts paused, so the player
+    /// can get ready before resuming.
+    pub fn continue_game(&mut self) -> Result<(), JsValue> {
+        if self.state != GameState::Menu {
+            return Err(JsValue::from("Not in the menu"));
+        }
+
+        let saved_game = utils::local_storage()
+            .and_then(|storage| storage.get_item(SAVED_GAME_STORAGE_KEY).ok().flatten())
+            .ok_or_else(|| JsValue::from("No saved game"))?;
+        let restored = SavedGame::from_base64(&saved_game).and_then(|saved_game| {
+            Engine::restore(&saved_game.engine).map(|engine| (engine, saved_game))
+        });
+        let (engine, saved_game) = match restored {
+            Ok(restored) => restored,
+            Err(err) => {
+                clear_saved_game();
+                return Err(JsValue::from(format!("Saved game is corrupt: {}", err)));
+            }
+        };
+
+        self.engine = engine;
+        self.mode = saved_game.mode;
+        self.input.set_handling(saved_game.replay.handling);
+        self.recording = saved_game.replay;
+        self.timestep = FixedTimestep::resume_at(saved_game.frame);
+        // Keys held when the game was saved aren't held any more. Record the
+        // release so the replay drops them at the same frame.
+        self.apply_input(ReplayInput::ReleaseAll);
+        // Saves don't keep real time, so the timer resumes from the steps run.
+        self.play_time_ms = saved_game.frame as f64 * STEP_MS;
+        self.finesse = FinesseTracker::resume(&self.engine, saved_game.finesse_faults);
+        self.state = GameState::Paused;
+        self.draw();
+        display::show_pause_menu().expect("Expected `show_pause_menu` call to succeed");
+        Ok(())
+    }
+
+    pub fn mode(&self) -> GameMode {
+        self.mode
+    }
+
+    /// Picks the mode of the next game from the menu.
+    pub fn set_mode(&mut self, mode: GameMode) {
+        if self.state != GameState::Menu {
+            return;
+        }
+        self.mode = mode;
+        self.engine.set_rules(mode.rules(self.engine.rules()));
+        self.reset();
+    }
+
+    /// Real time played in the current game, in milliseconds.
+    pub fn play_time_ms(&self) -> f64 {
+        self.play_time_ms
+    }
+
+    /// Inputs wasted over the optimum in the current game.
+    pub fn finesse_faults(&self) -> u32 {
+        self.finesse.faults()
+    }
+
+    /// The name stored with new high scores, if one was set.
+    pub fn player_name(&self) -> Option<String> {
+        utils::local_storage()
+            .and_then(|storage| storage.get_item(PLAYER_NAME_STORAGE_KEY).ok().flatten())
+            .filter(|name| !name.trim().is_empty())
+    }
+
+    pub fn set_player_name(&mut self, name: &str) {
+        if let Some(storage) = utils::local_storage() {
+            if let Err(err) = storage.set_item(PLAYER_NAME_STORAGE_KEY, name) {
+                log(&format!("Error saving player name: {:?}", err));
+            }
+        }
+    }
+
+    /// The best result in the current mode: the fastest time in
+    /// milliseconds for modes ranked by time, otherwise the best score.
+    /// `0` before any game is finished.
+    pub fn personal_best(&self) -> f64 {
+        let leaderboard = load_leaderboard();
+        let best = match leaderboard.personal_best(self.mode) {
+            Some(best) => best,
+            None => return 0.0,
+        };
+        if self.mode.ranks_by_time() {
+            best.time_ms
+        } else {
+            f64::from(best.score)
+        }
+    }
+
+    pub fn rotation_system(&self) -> RotationSystemKind {
+        self.engine.rotation_system().kind()
+    }
+
+    pub fn stats(&self) -> Stats {
+        self.engine.stats()
+    }
+
+    pub fn preview_count(&self) -> usize {
+        self.engine.rules().preview_count
+    }
+
+    /// Shows between 1 and 6 upcoming pieces.
+    pub fn set_preview_count(&mut self, preview_count: usize) {
+        let mut rules = self.engine.rules();
+        rules.preview_count = preview_count;
+        self.engine.set_rules(rules);
+    }
+
+    pub fn set_handling(&mut self, das_ms: f64, arr_ms: f64, soft_drop_factor: f64) {
+        self.input.set_handling(Handling {
+            das_ms,
+            arr_ms,
+            soft_drop_factor,
+        });
+    }
+
+    pub fn set_lock_delay(&mut self, lock_delay_ms: f64, max_lock_resets: u32) {
+        let mut rules = self.engine.rules();
+        rules.lock_delay_ms = lock_delay_ms;
+        rules.max_lock_resets = max_lock_resets;
+        self.engine.set_rules(rules);
+    }
+
+    /// Registers a callback that receives the final `Stats` when the game
+    /// ends.
+    pub fn set_on_game_over(&mut self, callback: Function) {
+        self.on_game_over = Some(callback);
+    }
+
+    /// Plays back the game that just ended from its first frame.
+    pub fn watch_replay(&mut self) {
+        if self.state != GameState::GameOver {
+            return;
+        }
+        self.start_playback(self.recording.clone());
+        display::hide_results().expect("Expected `hide_results` call to succeed");
+    }
+
+    /// The last recorded game in the base64 replay format.
+    pub fn export_replay(&self) -> String {
+        self.recording.to_base64()
+    }
+
+    /// Plays a replay exported by `export_replay`. Closing it returns to
+    /// the menu.
+    pub fn load_replay(&mut self, text: &str) -> Result<(), JsValue> {
+        if !matches!(self.state, GameState::Menu | GameState::GameOver) {
+            return Err(JsValue::from("A game is in progress"));
+        }
+        let replay = Replay::from_base64(text).map_err(JsValue::from)?;
+        display::hide_results().expect("Expected `hide_results` call to succeed");
+        self.start_playback(replay);
+        self.playback_origin = GameState::Menu;
+        Ok(())
+    }
+
+    /// Leaves the replay for wherever it was started from.
+    pub fn stop_replay(&mut self) {
+        if self.playback.take().is_none() {
+            return;
+        }
+        display::hide_replay_controls().expect("Expected `hide_replay_controls` call to succeed");
+        if self.playback_origin == GameState::GameOver {
+            self.state = GameState::GameOver;
+            self.show_results();
+        } else {
+            self.quit_to_menu();
+        }
+    }
+
+    /// Sets the playback rate, e.g. `2.0` for double speed or `0.0` to hold
+    /// the current frame.
+    pub fn set_replay_speed(&mut self, speed: f64) {
+        if let Some(playback) = &mut self.playback {
+            playback.set_speed(speed);
+        }
+    }
+
+    pub fn replay_speed(&self) -> f64 {
+        self.playback.as_ref().map_or(1.0, ReplayPlayer::speed)
+    }
+
+    pub fn seek_replay(&mut self, frame: u64) {
+        if let Some(playback) = &mut self.playback {
+            playback.seek(frame);
+            self.clear_label.clear();
+            self.clear_label_ms = 0.0;
+        }
+    }
+
+    pub fn replay_frame(&self) -> u64 {
+        self.playback.as_ref().map_or(0, ReplayPlayer::frame)
+    }
+
+    /// The length of the replay being watched in logic steps.
+    pub fn replay_length(&self) -> u64 {
+        self.playback
+            .as_ref()
+            .map_or(self.recording.frames, |playback| playback.replay().frames)
     }
 
     pub fn hide_game(&self) {
@@ -151,149 +569,427 @@ impl Game {
 }
 
 impl Game {
-    fn new_bag(&mut self) {
-        let mut pieces = vec![
-            PieceType::Straight,
-            PieceType::LLeft,
-            PieceType::LRight,
-            PieceType::Square,
-            PieceType::S,
-            PieceType::Z,
-            PieceType::T,
-        ];
+    /// Applies `input` to the live game and records it for the replay.
+    fn apply_input(&mut self, input: ReplayInput) {
+        self.recording.record(self.timestep.frame(), input);
+        self.finesse.before_input(input);
+        replay::apply_input(&mut self.engine, &mut self.input, input);
+        self.finesse.after_update(&self.engine);
+    }
+
+    fn start_playback(&mut self, replay: Replay) {
+        self.playback_origin = self.state;
+        self.playback = Some(ReplayPlayer::new(replay));
+        self.state = GameState::Replay;
+        self.clear_label.clear();
+        self.clear_label_ms = 0.0;
+        display::show_replay_controls().expect("Expected `show_replay_controls` call to succeed");
+    }
 
-        for i in (0..pieces.len()).rev() {
-            let j = (Math::random() * (i + 1) as f64) as usize;
-            pieces.swap(i, j);
+    fn start_recording(&mut self) {
+        self.timestep = FixedTimestep::new();
+        self.play_time_ms = 0.0;
+        self.finesse = FinesseTracker::new(&self.engine);
+        self.recording = Replay::start(&self.engine, self.input.handling());
+    }
+
+    /// The engine being shown: the replay's while one is playing.
+    fn displayed_engine(&self) -> &Engine {
+        match &self.playback {
+            Some(playback) => playback.engine(),
+            None => &self.engine,
         }
+    }
 
-        self.bag = pieces;
-        self.bag_index = 0;
+    /// The timer shown in the sidebar, counting down in timed modes.
+    /// Replays count logic steps, as they don't record real time.
+    fn displayed_time_ms(&self) -> f64 {
+        let engine = self.displayed_engine();
+        if let Some(time_limit_ms) = engine.rules().time_limit_ms {
+            return (time_limit_ms - engine.elapsed_ms()).max(0.0);
+        }
+        match &self.playback {
+            Some(playback) => playback.frame() as f64 * STEP_MS,
+            None => self.play_time_ms,
+        }
+    }
+
+    fn step(&mut self) {
+        self.input.update(&mut self.engine, STEP_MS);
+        self.engine.tick(STEP_MS);
+        self.finesse.after_update(&self.engine);
+
+        let clear_events = self.engine.take_clear_events();
+        self.show_clear_events(&clear_events, STEP_MS);
     }
 
-    fn get_next_piece(&mut self) -> PieceType {
-        if self.bag_index >= self.bag.len() {
-            self.new_bag();
+    /// Labels the latest of `clear_events` in the sidebar, or lets the
+    /// current label run down by `elapsed_ms` if there were none.
+    fn show_clear_events(&mut self, clear_events: &[ClearEvent], elapsed_ms: f64) {
+        if let Some(event) = clear_events.last() {
+            self.clear_label = event.label();
+            self.clear_label_ms = CLEAR_LABEL_DURATION_MS;
+        } else if self.clear_label_ms > 0.0 {
+            self.clear_label_ms -= elapsed_ms;
+            if self.clear_label_ms <= 0.0 {
+                self.clear_label.clear();
+            }
         }
-        let piece = self.bag[self.bag_index].clone();
-        self.bag_index += 1;
-        piece
     }
+
+    fn draw(&self) {
+        let engine = self.displayed_engine();
+        let held_piece = engine
+            .held_piece()
+            .map(|piece_type| engine.preview_piece(piece_type));
+        let next_pieces: Vec<_> = engine
+            .next_pieces()
+            .map(|piece_type| engine.preview_piece(piece_type))
+            .collect();
+
+        self.display.draw(
+            engine.board(),
+            held_piece.as_ref(),
+            &next_pieces,
+            &engine.ghost_piece(),
+        );
+
+        if self.state == GameState::Paused {
+            self.display
+                .draw_pause_overlay()
+                .expect("Expected `draw_pause_overlay` call to succeed");
+        }
+    }
+
+    /// Starts a fresh engine with the current modes and rules, so a new game
+    /// doesn't need a page reload.
+    fn reset(&mut self) {
+        self.engine = Engine::with_rules(
+            random_seed(),
+            self.engine.randomizer().kind().build(),
+            self.engine.rotation_system().kind().build(),
+            self.engine.rules(),
+        );
+        self.input.release_all();
+        self.gestures.cancel();
+        self.clear_label.clear();
+        self.clear_label_ms = 0.0;
+        self.timestep = FixedTimestep::new();
+        self.play_time_ms = 0.0;
+        self.finesse = FinesseTracker::new(&self.engine);
+        self.playback = None;
+
+        display::hide_pause_menu().expect("Expected `hide_pause_menu` call to succeed");
+        display::hide_results().expect("Expected `hide_results` call to succeed");
+        display::hide_replay_controls().expect("Expected `hide_replay_controls` call to succeed");
+    }
+
+    fn show_results(&self) {
+        let leaderboard = load_leaderboard();
+        let stats = self.engine.stats();
+        let pieces_per_second = if self.play_time_ms > 0.0 {
+            f64::from(stats.pieces) / (self.play_time_ms / 1000.0)
+        } else {
+            0.0
+        };
+        display::show_results(&Results {
+            title: if self.engine.goal_reached() {
+                "COMPLETE"
+            } else if self.engine.is_time_up() && self.engine.top_out().is_none() {
+                "TIME UP"
+            } else {
+                "GAME OVER"
+            },
+            mode: self.mode,
+            stats: &stats,
+            time_ms: self.play_time_ms,
+            pieces_per_second,
+            finesse_faults: self.finesse.faults(),
+            high_scores: leaderboard.entries(self.mode),
+            rank: self.last_rank,
+        })
+        .expect("Expected `show_results` call to succeed");
+    }
+
+    fn end_game(&mut self) {
+        self.state = GameState::GameOver;
+        clear_saved_game();
+        self.recording.frames = self.timestep.frame();
+        let stats = self.engine.stats();
+
+        // A sprint that tops out has no time to rank.
+        self.last_rank = None;
+        if !self.mode.ranks_by_time() || self.engine.goal_reached() {
+            let mut leaderboard = load_leaderboard();
+            self.last_rank = leaderboard.submit(
+                self.mode,
+                HighScore {
+                    score: stats.score,
+                    lines: stats.lines,
+                    level: stats.level,
+                    time_ms: self.play_time_ms,
+                    date_ms: Date::now(),
+                    name: self.player_name(),
+                },
+            );
+            if let Some(storage) = utils::local_storage() {
+                if let Err(err) =
+                    storage.set_item(LEADERBOARD_STORAGE_KEY, &leaderboard.serialize())
+                {
+                    log(&format!("Error saving high scores: {:?}", err));
+                }
+            }
+        }
+
+        self.show_results();
+
+        if let Some(callback) = &self.on_game_over {
+            if let Err(err) = callback.call1(&JsValue::NULL, &stats.into()) {
+                log(&format!("Error in game over callback: {:?}", err));
+            }
+        }
+    }
+}
+
+/// Notices when the page is hidden or loses focus, so the game can pause
+/// itself before the player misses anything.
+struct FocusWatcher {
+    focus_lost: Rc<Cell<bool>>,
+    on_visibility_change: Closure<dyn FnMut()>,
+    on_blur: Closure<dyn FnMut()>,
+}
+
+impl FocusWatcher {
+    fn new() -> Result<FocusWatcher, JsValue> {
+        let window = web_sys::window().expect("no global `window` exists");
+        let document = window.document().expect("should have a document on window");
+        let focus_lost = Rc::new(Cell::new(false));
+
+        let on_visibility_change = {
+            let focus_lost = Rc::clone(&focus_lost);
+            let document = document.clone();
+            Closure::<dyn FnMut()>::new(move || {
+                if document.hidden() {
+                    focus_lost.set(true);
+                }
+            })
+        };
+        let on_blur = {
+            let focus_lost = Rc::clone(&focus_lost);
+            Closure::<dyn FnMut()>::new(move || focus_lost.set(true))
+        };
+
+        document.add_event_listener_with_callback(
+            "visibilitychange",
+            on_visibility_change.as_ref().unchecked_ref(),
+        )?;
+        window.add_event_listener_with_callback("blur", on_blur.as_ref().unchecked_ref())?;
+
+        Ok(FocusWatcher {
+            focus_lost,
+            on_visibility_change,
+            on_blur,
+        })
+    }
+
+    /// Whether focus was lost since the last call.
+    fn take_focus_lost(&self) -> bool {
+        self.focus_lost.replace(false)
+    }
+}
+
+impl Drop for FocusWatcher {
+    fn drop(&mut self) {
+        let window = web_sys::window().expect("no global `window` exists");
+        let document = window.document().expect("should have a document on window");
+        // The closures are freed with the watcher, so they must not be
+        // called again.
+        let _ = document.remove_event_listener_with_callback(
+            "visibilitychange",
+            self.on_visibility_change.as_ref().unchecked_ref(),
+        );
+        let _ = window
+            .remove_event_listener_with_callback("blur", self.on_blur.as_ref().unchecked_ref());
+    }
+}
+
+/// Seeds a game from the browser's RNG when the caller doesn't pick a seed.
+fn random_seed() -> u32 {
+    (Math::random() * u32::MAX as f64) as u32
 }
 
 #[wasm_bindgen]
 impl Game {
-    pub fn move_cursor_left(&mut self) {
-        let mut next_piece = self.board.current_piece.clone();
-        next_piece.col -= 1;
-        if self.board.is_valid_position(&next_piece) {
-            self.board.current_piece = next_piece;
+    /// Handles a browser `keydown` for `code`. Held keys repeat through DAS
+    /// and ARR in `tick`, so repeated `keydown` events should be ignored.
+    pub fn key_down(&mut self, code: &str) {
+        if let Some(action) = self.rebinding.take() {
+            self.key_bindings.bind(code, action);
+            self.save_key_bindings();
+            self.render_keybinds();
+            return;
         }
-    }
 
-    pub fn move_cursor_right(&mut self) {
-        let mut next_piece = self.board.current_piece.clone();
-        next_piece.col += 1;
-        if self.board.is_valid_position(&next_piece) {
-            self.board.current_piece = next_piece;
+        if let Some(action) = self.key_bindings.action_for(code) {
+            self.action_down(action);
         }
     }
 
-    pub fn rotate_current_piece(&mut self) {
-        let mut next_piece = self.board.current_piece.clone();
-        next_piece.rotate();
+    /// Handles a touch starting on the playfield. Coordinates are CSS pixels
+    /// and `time` is the event's timestamp in milliseconds.
+    pub fn touch_start(&mut self, x: f64, y: f64, time: f64) {
+        self.gestures.start(x, y, time);
+    }
+
+    pub fn touch_move(&mut self, x: f64, y: f64) {
+        for gesture in self.gestures.move_to(x, y) {
+            self.apply_gesture(gesture);
+        }
+    }
 
-        let kick_tests = [
-            (0, 0), 
-            (-1, 0),
-            (1, 0), 
-            (0, 1),
-            (-2, 0),
-            (2, 0), 
-            (0, 2), 
-        ];
+    pub fn touch_end(&mut self, x: f64, y: f64, time: f64) {
+        for gesture in self.gestures.end(x, y, time) {
+            self.apply_gesture(gesture);
+        }
+    }
 
-        for (col_offset, row_offset) in kick_tests.iter() {
-            let mut kicked_piece = next_piece.clone();
-            kicked_piece.col += col_offset;
-            kicked_piece.row += row_offset;
+    pub fn touch_cancel(&mut self) {
+        self.gestures.cancel();
+    }
 
-            if self.board.is_valid_position(&kicked_piece) {
-                self.board.current_piece = kicked_piece;
-                return;
-            }
+    pub fn key_up(&mut self, code: &str) {
+        if let Some(action) = self.key_bindings.action_for(code) {
+            self.action_up(action);
         }
     }
 
-    pub fn hard_drop_current_piece(&mut self) {
-        loop {
-            let mut next_piece = self.board.current_piece.clone();
-            next_piece.row += 1;
-            if self.board.is_valid_position(&next_piece) {
-                self.board.current_piece = next_piece;
+    pub fn action_down(&mut self, action: Action) {
+        if action == Action::Pause {
+            if self.state == GameState::Paused {
+                self.resume_game();
             } else {
-                self.board.lock_piece();
-                let lines_cleared = self.board.clear_lines();
-                self.score += match lines_cleared {
-                    1 => 100,
-                    2 => 300,
-                    3 => 500,
-                    4 => 800,
-                    _ => 0,
-                };
-                self.board.current_piece = PieceState::new(self.next_piece.clone(), self.cursor_x);
-
-                self.can_hold_this_turn = true;
-                let new_piece_type = self.get_next_piece();
-                self.next_piece = new_piece_type;
-                break;
+                self.pause_game();
             }
+            return;
         }
+
+        if self.state != GameState::Running {
+            return;
+        }
+
+        self.apply_input(ReplayInput::Press(action));
     }
 
-    pub fn hold_piece(&mut self) {
-        if !self.can_hold_this_turn {
+    pub fn action_up(&mut self, action: Action) {
+        if self.state != GameState::Running {
             return;
         }
 
-        self.can_hold_this_turn = false;
+        self.apply_input(ReplayInput::Release(action));
+    }
 
-        let current_piece_type = self.board.current_piece.piece_type.clone();
+    /// The next key pressed will be bound to `action`.
+    pub fn begin_rebind(&mut self, action: Action) {
+        self.rebinding = Some(action);
+        self.render_keybinds();
+    }
 
-        if let Some(held) = self.held_piece.take() {
-            self.board.current_piece = PieceState::new(held, self.cursor_x);
-            self.held_piece = Some(current_piece_type);
-        } else {
-            self.held_piece = Some(current_piece_type);
-            self.board.current_piece = PieceState::new(self.next_piece.clone(), self.cursor_x);
+    pub fn unbind_key(&mut self, code: &str) {
+        self.key_bindings.unbind(code);
+        self.save_key_bindings();
+        self.render_keybinds();
+    }
+
+    pub fn reset_key_bindings(&mut self) {
+        self.key_bindings = KeyBindings::default();
+        self.save_key_bindings();
+        self.render_keybinds();
+    }
+
+    /// The bindings in their `action=Code,Code;...` text form.
+    pub fn key_bindings(&self) -> String {
+        self.key_bindings.serialize()
+    }
+
+    pub fn set_key_bindings(&mut self, serialized: &str) -> Result<(), JsValue> {
+        self.key_bindings = KeyBindings::deserialize(serialized).map_err(JsValue::from)?;
+        self.save_key_bindings();
+        self.render_keybinds();
+        Ok(())
+    }
+}
 
-            self.can_hold_this_turn = true;
-            let new_piece_type = self.get_next_piece();
-            self.next_piece = new_piece_type;
+impl Game {
+    fn apply_gesture(&mut self, gesture: Gesture) {
+        if self.state != GameState::Running {
+            return;
         }
+
+        // Gestures act like a quick press and release, so they are recorded
+        // like any other input.
+        let action = match gesture {
+            Gesture::Tap => Action::RotateClockwise,
+            Gesture::ShiftLeft => Action::MoveLeft,
+            Gesture::ShiftRight => Action::MoveRight,
+            Gesture::SoftDrop => Action::SoftDrop,
+            Gesture::HardDrop => Action::HardDrop,
+        };
+        self.action_down(action);
+        self.action_up(action);
     }
 
-    pub fn soft_drop(&mut self) {
-        let mut next_piece = self.board.current_piece.clone();
-        next_piece.row += 1;
-        if self.board.is_valid_position(&next_piece) {
-            self.board.current_piece = next_piece;
-        } else {
-            self.board.lock_piece();
-            let lines_cleared = self.board.clear_lines();
-            self.score += match lines_cleared {
-                1 => 100,
-                2 => 300,
-                3 => 500,
-                4 => 800,
-                _ => 0,
-            };
-            self.board.current_piece = PieceState::new(self.next_piece.clone(), self.cursor_x);
-
-            self.can_hold_this_turn = true;
-            let new_piece_type = self.get_next_piece();
-            self.next_piece = new_piece_type;
+    fn poll_gamepad(&mut self) {
+        let changes = match gamepad::poll_buttons() {
+            Some(buttons) => self.gamepad.update(&buttons),
+            None => self.gamepad.release_all(),
+        };
+
+        for (action, pressed) in changes {
+            if pressed {
+                self.action_down(action);
+            } else {
+                self.action_up(action);
+            }
+        }
+    }
+
+    fn render_keybinds(&self) {
+        display::render_keybinds(&self.key_bindings, self.rebinding)
+            .expect("Expected `render_keybinds` call to succeed");
+    }
+
+    fn save_key_bindings(&self) {
+        if let Some(storage) = utils::local_storage() {
+            if let Err(err) =
+                storage.set_item(KEY_BINDINGS_STORAGE_KEY, &self.key_bindings.serialize())
+            {
+                log(&format!("Error saving key bindings: {:?}", err));
+            }
         }
     }
 }
 
+fn clear_saved_game() {
+    if let Some(storage) = utils::local_storage() {
+        if let Err(err) = storage.remove_item(SAVED_GAME_STORAGE_KEY) {
+            log(&format!("Error clearing saved game: {:?}", err));
+        }
+    }
+    display::set_continue_visible(false).expect("Expected `set_continue_visible` call to succeed");
+}
+
+fn load_leaderboard() -> Leaderboard {
+    utils::local_storage()
+        .and_then(|storage| storage.get_item(LEADERBOARD_STORAGE_KEY).ok().flatten())
+        .and_then(|serialized| Leaderboard::deserialize(&serialized).ok())
+        .unwrap_or_default()
+}
+
+/// Reads saved bindings, falling back to the defaults if there are none or
+/// they can't be parsed.
+fn load_key_bindings() -> KeyBindings {
+    utils::local_storage()
+        .and_then(|storage| storage.get_item(KEY_BINDINGS_STORAGE_KEY).ok().flatten())
+        .and_then(|serialized| KeyBindings::deserialize(&serialized).ok())
+        .unwrap_or_default()
+}
@@ -0,0 +1,95 @@
+use wasm_bindgen::JsCast;
+use web_sys::{Gamepad, GamepadButton};
+
+use crate::keybinds::Action;
+
+/// How far an analog stick must be pushed to count as a D-pad press.
+const STICK_DEADZONE: f64 = 0.5;
+
+const DPAD_UP: usize = 12;
+const DPAD_DOWN: usize = 13;
+const DPAD_LEFT: usize = 14;
+const DPAD_RIGHT: usize = 15;
+
+/// Maps buttons of the W3C "standard" gamepad layout to actions and turns
+/// polled button states into press and release events.
+#[derive(Clone, Debug)]
+pub struct GamepadInput {
+    mapping: Vec<(usize, Action)>,
+    pressed: Vec<bool>,
+}
+
+impl Default for GamepadInput {
+    fn default() -> Self {
+        GamepadInput {
+            mapping: vec![
+                (0, Action::RotateCounterClockwise),
+                (1, Action::RotateClockwise),
+                (2, Action::Rotate180),
+                (3, Action::Hold),
+                (4, Action::Hold),
+                (5, Action::Hold),
+                (9, Action::Pause),
+                (DPAD_UP, Action::HardDrop),
+                (DPAD_DOWN, Action::SoftDrop),
+                (DPAD_LEFT, Action::MoveLeft),
+                (DPAD_RIGHT, Action::MoveRight),
+            ],
+            pressed: Vec::new(),
+        }
+    }
+}
+
+impl GamepadInput {
+    /// Compares `buttons` with the previous poll and returns the actions
+    /// whose buttons changed, with `true` for a press.
+    pub fn update(&mut self, buttons: &[bool]) -> Vec<(Action, bool)> {
+        let mut changes = Vec::new();
+        for &(button, action) in self.mapping.iter() {
+            let was_pressed = self.pressed.get(button).copied().unwrap_or(false);
+            let is_pressed = buttons.get(button).copied().unwrap_or(false);
+            if was_pressed != is_pressed {
+                changes.push((action, is_pressed));
+            }
+        }
+        self.pressed = buttons.to_vec();
+        changes
+    }
+
+    /// Releases everything that was held, e.g. when the pad disconnects.
+    pub fn release_all(&mut self) -> Vec<(Action, bool)> {
+        self.update(&[])
+    }
+}
+
+/// Reads the buttons of the first connected gamepad, folding the left stick
+/// into the D-pad. Returns `None` when no gamepad is connected.
+pub fn poll_buttons() -> Option<Vec<bool>> {
+    let window = web_sys::window().expect("no global `window` exists");
+    let gamepads = window.navigator().get_gamepads().ok()?;
+    let gamepad = gamepads
+        .iter()
+        .find_map(|gamepad| gamepad.dyn_into::<Gamepad>().ok())?;
+
+    let mut buttons: Vec<bool> = gamepad
+        .buttons()
+        .iter()
+        .map(|button| {
+            button
+                .dyn_into::<GamepadButton>()
+                .map(|button| button.pressed())
+                .unwrap_or(false)
+        })
+        .collect();
+    if buttons.len() <= DPAD_RIGHT {
+        buttons.resize(DPAD_RIGHT + 1, false);
+    }
+
+    let axes = gamepad.axes();
+    let axis = |index: u32| axes.get(index).as_f64().unwrap_or(0.0);
+    buttons[DPAD_LEFT] |= axis(0) < -STICK_DEADZONE;
+    buttons[DPAD_RIGHT] |= axis(0) > STICK_DEADZONE;
+    buttons[DPAD_DOWN] |= axis(1) > STICK_DEADZONE;
+
+    Some(buttons)
+}
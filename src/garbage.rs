@@ -0,0 +1,42 @@
+use crate::random::Rng;
+
+/// The colour name of garbage blocks, drawn with the `--garbage` CSS
+/// variable like the piece colours.
+pub const GARBAGE_COLOR: &str = "garbage";
+
+/// Picks the open column of each garbage row. It has its own `Rng`, so
+/// adding garbage doesn't change the pieces dealt.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HoleGenerator {
+    rng: Rng,
+    last_hole: Option<usize>,
+}
+
+impl HoleGenerator {
+    pub fn new(seed: u32) -> HoleGenerator {
+        HoleGenerator {
+            // The piece `Rng` starts from `seed`; inverting it keeps the two
+            // sequences unrelated.
+            rng: Rng::new(!seed),
+            last_hole: None,
+        }
+    }
+
+    /// A column in `0..width`, never the same as the row before, so every
+    /// row has to be dug out on its own.
+    pub fn next_hole(&mut self, width: usize) -> usize {
+        let hole = match self.last_hole {
+            Some(last_hole) if width > 1 => {
+                let hole = self.rng.below(width - 1);
+                if hole >= last_hole {
+                    hole + 1
+                } else {
+                    hole
+                }
+            }
+            _ => self.rng.below(width),
+        };
+        self.last_hole = Some(hole);
+        hole
+    }
+}
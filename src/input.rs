@@ -0,0 +1,170 @@
+use crate::engine::Engine;
+
+/// Auto-shift and soft drop timings, in milliseconds.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Handling {
+    /// Delayed auto shift: how long a direction is held before repeating.
+    pub das_ms: f64,
+    /// Auto repeat rate: time between repeated shifts. `0` shifts straight
+    /// to the wall.
+    pub arr_ms: f64,
+    /// How many times faster than gravity a held soft drop falls.
+    pub soft_drop_factor: f64,
+}
+
+impl Default for Handling {
+    fn default() -> Self {
+        Handling {
+            das_ms: 167.0,
+            arr_ms: 33.0,
+            soft_drop_factor: 20.0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    Left,
+    Right,
+}
+
+/// Turns held keys into frame-accurate piece movement, independent of the
+/// operating system's key repeat.
+#[derive(Clone, Debug, Default)]
+pub struct InputHandler {
+    handling: Handling,
+    left_held: bool,
+    right_held: bool,
+    active_direction: Option<Direction>,
+    das_timer: f64,
+    arr_timer: f64,
+    soft_drop_held: bool,
+    soft_drop_timer: f64,
+}
+
+impl InputHandler {
+    pub fn new(handling: Handling) -> InputHandler {
+        InputHandler {
+            handling,
+            ..InputHandler::default()
+        }
+    }
+
+    pub fn handling(&self) -> Handling {
+        self.handling
+    }
+
+    pub fn set_handling(&mut self, handling: Handling) {
+        self.handling = handling;
+    }
+
+    /// Shifts once straight away, then starts charging DAS. The most recently
+    /// pressed direction wins while both are held.
+    pub fn press(&mut self, engine: &mut Engine, direction: Direction) {
+        match direction {
+            Direction::Left => self.left_held = true,
+            Direction::Right => self.right_held = true,
+        }
+        self.start_shift(engine, direction);
+    }
+
+    pub fn release(&mut self, engine: &mut Engine, direction: Direction) {
+        match direction {
+            Direction::Left => self.left_held = false,
+            Direction::Right => self.right_held = false,
+        }
+
+        if self.active_direction != Some(direction) {
+            return;
+        }
+
+        self.active_direction = None;
+        match direction {
+            Direction::Left if self.right_held => self.start_shift(engine, Direction::Right),
+            Direction::Right if self.left_held => self.start_shift(engine, Direction::Left),
+            _ => {}
+        }
+    }
+
+    pub fn press_soft_drop(&mut self, engine: &mut Engine) {
+        if self.soft_drop_held {
+            return;
+        }
+        self.soft_drop_held = true;
+        self.soft_drop_timer = 0.0;
+        engine.soft_drop();
+    }
+
+    pub fn release_soft_drop(&mut self) {
+        self.soft_drop_held = false;
+    }
+
+    /// Forgets every held key, e.g. when the window loses focus.
+    pub fn release_all(&mut self) {
+        *self = InputHandler::new(self.handling);
+    }
+
+    /// Advances auto-shift and soft drop by `delta_time`. Call this from the
+    /// same loop that ticks the engine.
+    pub fn update(&mut self, engine: &mut Engine, delta_time: f64) {
+        if let Some(direction) = self.active_direction {
+            let was_charged = self.das_timer >= self.handling.das_ms;
+            self.das_timer += delta_time;
+
+            if self.das_timer >= self.handling.das_ms {
+                let elapsed = if was_charged {
+                    delta_time
+                } else {
+                    self.das_timer - self.handling.das_ms
+                };
+                self.auto_shift(engine, direction, elapsed);
+            }
+        }
+
+        if self.soft_drop_held {
+            let interval = engine.drop_interval_ms() / self.handling.soft_drop_factor.max(1.0);
+            self.soft_drop_timer += delta_time;
+            while self.soft_drop_timer >= interval {
+                self.soft_drop_timer -= interval;
+                if !engine.soft_drop() {
+                    self.soft_drop_timer = 0.0;
+                    break;
+                }
+            }
+        }
+    }
+}
+
+impl InputHandler {
+    fn start_shift(&mut self, engine: &mut Engine, direction: Direction) {
+        self.active_direction = Some(direction);
+        self.das_timer = 0.0;
+        // The first repeat happens as soon as DAS is charged.
+        self.arr_timer = self.handling.arr_ms;
+        shift(engine, direction);
+    }
+
+    fn auto_shift(&mut self, engine: &mut Engine, direction: Direction, elapsed: f64) {
+        if self.handling.arr_ms <= 0.0 {
+            while shift(engine, direction) {}
+            return;
+        }
+
+        self.arr_timer += elapsed;
+        while self.arr_timer >= self.handling.arr_ms {
+            self.arr_timer -= self.handling.arr_ms;
+            if !shift(engine, direction) {
+                // Stay charged against the wall without banking repeats.
+                self.arr_timer = self.handling.arr_ms;
+                break;
+            }
+        }
+    }
+}
+
+fn shift(engine: &mut Engine, direction: Direction) -> bool {
+    match direction {
+        Direction::Left => engine.move_left(),
+        Direction::Right => engine.move_right(),
+    }
+}
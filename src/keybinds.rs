@@ -0,0 +1,182 @@
+use wasm_bindgen::prelude::wasm_bindgen;
+
+/// Everything a player can ask the game to do, independent of the device.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Action {
+    MoveLeft,
+    MoveRight,
+    SoftDrop,
+    HardDrop,
+    RotateClockwise,
+    RotateCounterClockwise,
+    Rotate180,
+    Hold,
+    Pause,
+}
+
+impl Action {
+    pub const ALL: [Action; 9] = [
+        Action::MoveLeft,
+        Action::MoveRight,
+        Action::SoftDrop,
+        Action::HardDrop,
+        Action::RotateClockwise,
+        Action::RotateCounterClockwise,
+        Action::Rotate180,
+        Action::Hold,
+        Action::Pause,
+    ];
+
+    /// The stable name used in serialized bindings.
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::MoveLeft => "move_left",
+            Action::MoveRight => "move_right",
+            Action::SoftDrop => "soft_drop",
+            Action::HardDrop => "hard_drop",
+            Action::RotateClockwise => "rotate_cw",
+            Action::RotateCounterClockwise => "rotate_ccw",
+            Action::Rotate180 => "rotate_180",
+            Action::Hold => "hold",
+            Action::Pause => "pause",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Action> {
+        Action::ALL
+            .iter()
+            .copied()
+            .find(|action| action.name() == name)
+    }
+
+    /// The text shown in the keybinds panel.
+    pub fn label(self) -> &'static str {
+        match self {
+            Action::MoveLeft => "MOVE LEFT",
+            Action::MoveRight => "MOVE RIGHT",
+            Action::SoftDrop => "SOFT DROP",
+            Action::HardDrop => "HARD DROP",
+            Action::RotateClockwise => "ROTATE CW",
+            Action::RotateCounterClockwise => "ROTATE CCW",
+            Action::Rotate180 => "ROTATE 180",
+            Action::Hold => "HOLD PIECE",
+            Action::Pause => "PAUSE",
+        }
+    }
+}
+
+/// Maps `KeyboardEvent.code` values to actions. A key drives at most one
+/// action; an action may have several keys.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KeyBindings {
+    bindings: Vec<(String, Action)>,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        let mut key_bindings = KeyBindings::empty();
+        for (code, action) in [
+            ("KeyA", Action::MoveLeft),
+            ("ArrowLeft", Action::MoveLeft),
+            ("KeyD", Action::MoveRight),
+            ("ArrowRight", Action::MoveRight),
+            ("KeyS", Action::SoftDrop),
+            ("ArrowDown", Action::SoftDrop),
+            ("Space", Action::HardDrop),
+            ("KeyR", Action::RotateClockwise),
+            ("ArrowUp", Action::RotateClockwise),
+            ("KeyZ", Action::RotateCounterClockwise),
+            ("ControlLeft", Action::RotateCounterClockwise),
+            ("KeyX", Action::Rotate180),
+            ("KeyC", Action::Hold),
+            ("ShiftLeft", Action::Hold),
+            ("ShiftRight", Action::Hold),
+            ("Escape", Action::Pause),
+            ("KeyP", Action::Pause),
+        ] {
+            key_bindings.bind(code, action);
+        }
+        key_bindings
+    }
+}
+
+impl KeyBindings {
+    pub fn empty() -> KeyBindings {
+        KeyBindings {
+            bindings: Vec::new(),
+        }
+    }
+
+    pub fn action_for(&self, code: &str) -> Option<Action> {
+        self.bindings
+            .iter()
+            .find(|(bound_code, _)| bound_code == code)
+            .map(|&(_, action)| action)
+    }
+
+    pub fn keys_for(&self, action: Action) -> impl Iterator<Item = &str> {
+        self.bindings
+            .iter()
+            .filter(move |&&(_, bound_action)| bound_action == action)
+            .map(|(code, _)| code.as_str())
+    }
+
+    /// Binds `code` to `action`, taking it away from any other action.
+    pub fn bind(&mut self, code: &str, action: Action) {
+        self.unbind(code);
+        self.bindings.push((code.to_string(), action));
+    }
+
+    pub fn unbind(&mut self, code: &str) {
+        self.bindings.retain(|(bound_code, _)| bound_code != code);
+    }
+
+    /// Serializes to `action=Code,Code;action=Code`, in `Action::ALL` order.
+    pub fn serialize(&self) -> String {
+        Action::ALL
+            .iter()
+            .map(|&action| {
+                let keys: Vec<&str> = self.keys_for(action).collect();
+                format!("{}={}", action.name(), keys.join(","))
+            })
+            .collect::<Vec<_>>()
+            .join(";")
+    }
+
+    pub fn deserialize(serialized: &str) -> Result<KeyBindings, String> {
+        let mut key_bindings = KeyBindings::empty();
+        for entry in serialized.split(';').filter(|entry| !entry.is_empty()) {
+            let (name, keys) = entry
+                .split_once('=')
+                .ok_or_else(|| format!("Expected `action=keys`, found `{}`", entry))?;
+            let action =
+                Action::from_name(name).ok_or_else(|| format!("Unknown action `{}`", name))?;
+            for code in keys.split(',').filter(|code| !code.is_empty()) {
+                key_bindings.bind(code, action);
+            }
+        }
+        Ok(key_bindings)
+    }
+}
+
+/// A short, readable name for a `KeyboardEvent.code`.
+pub fn key_label(code: &str) -> String {
+    if let Some(letter) = code.strip_prefix("Key") {
+        return letter.to_string();
+    }
+    if let Some(digit) = code.strip_prefix("Digit") {
+        return digit.to_string();
+    }
+    match code {
+        "ArrowLeft" => "←".to_string(),
+        "ArrowRight" => "→".to_string(),
+        "ArrowUp" => "↑".to_string(),
+        "ArrowDown" => "↓".to_string(),
+        "ShiftLeft" | "ShiftRight" => "SHIFT".to_string(),
+        "ControlLeft" | "ControlRight" => "CTRL".to_string(),
+        "AltLeft" | "AltRight" => "ALT".to_string(),
+        "Escape" => "ESC".to_string(),
+        _ => code.to_uppercase(),
+    }
+}
@@ -0,0 +1,136 @@
+use crate::mode::GameMode;
+
+/// How many results each mode keeps.
+pub const LEADERBOARD_SIZE: usize = 10;
+
+/// Names are trimmed to this many characters.
+pub const MAX_NAME_LENGTH: usize = 16;
+
+/// One finished game on the leaderboard.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HighScore {
+    pub score: u32,
+    pub lines: u32,
+    pub level: u32,
+    pub time_ms: f64,
+    /// When the game ended, in milliseconds since the Unix epoch.
+    pub date_ms: f64,
+    pub name: Option<String>,
+}
+
+/// The best results per mode, best first.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Leaderboard {
+    tables: Vec<(GameMode, Vec<HighScore>)>,
+}
+
+impl Leaderboard {
+    pub fn new() -> Leaderboard {
+        Leaderboard::default()
+    }
+
+    pub fn entries(&self, mode: GameMode) -> &[HighScore] {
+        self.tables
+            .iter()
+            .find(|(table_mode, _)| *table_mode == mode)
+            .map_or(&[], |(_, entries)| entries.as_slice())
+    }
+
+    pub fn personal_best(&self, mode: GameMode) -> Option<&HighScore> {
+        self.entries(mode).first()
+    }
+
+    /// Adds `entry` to the table for `mode` and returns its rank, from `0`
+    /// for a new personal best, or `None` if it didn't make the table.
+    pub fn submit(&mut self, mode: GameMode, mut entry: HighScore) -> Option<usize> {
+        entry.name = entry.name.as_deref().and_then(clean_name);
+
+        let entries = match self
+            .tables
+            .iter_mut()
+            .find(|(table_mode, _)| *table_mode == mode)
+        {
+            Some((_, entries)) => entries,
+            None => {
+                self.tables.push((mode, Vec::new()));
+                &mut self.tables.last_mut().unwrap().1
+            }
+        };
+
+        // Ties go to the earlier game.
+        let rank = entries
+            .iter()
+            .position(|other| mode.compare(&entry, other).is_lt())
+            .unwrap_or(entries.len());
+        if rank >= LEADERBOARD_SIZE {
+            return None;
+        }
+        entries.insert(rank, entry);
+        entries.truncate(LEADERBOARD_SIZE);
+        Some(rank)
+    }
+
+    /// Serializes to one line per result:
+    /// `mode,score,lines,level,time_ms,date_ms,name`.
+    pub fn serialize(&self) -> String {
+        let mut lines = Vec::new();
+        for (mode, entries) in self.tables.iter() {
+            for entry in entries.iter() {
+                lines.push(format!(
+                    "{},{},{},{},{},{},{}",
+                    mode.name(),
+                    entry.score,
+                    entry.lines,
+                    entry.level,
+                    entry.time_ms,
+                    entry.date_ms,
+                    entry.name.as_deref().unwrap_or("")
+                ));
+            }
+        }
+        lines.join("\n")
+    }
+
+    pub fn deserialize(serialized: &str) -> Result<Leaderboard, String> {
+        let mut leaderboard = Leaderboard::new();
+        for line in serialized.lines().filter(|line| !line.is_empty()) {
+            let fields: Vec<&str> = line.splitn(7, ',').collect();
+            if fields.len() != 7 {
+                return Err(format!("Expected 7 fields, found `{}`", line));
+            }
+            let mode = GameMode::from_name(fields[0])
+                .ok_or_else(|| format!("Unknown mode `{}`", fields[0]))?;
+            let number = |index: usize| {
+                fields[index]
+                    .parse::<f64>()
+                    .map_err(|_| format!("Expected a number, found `{}`", fields[index]))
+            };
+            let entry = HighScore {
+                score: number(1)? as u32,
+                lines: number(2)? as u32,
+                level: number(3)? as u32,
+                time_ms: number(4)?,
+                date_ms: number(5)?,
+                name: clean_name(fields[6]),
+            };
+            leaderboard.submit(mode, entry);
+        }
+        Ok(leaderboard)
+    }
+}
+
+/// Keeps names printable, single-line and short; `None` if nothing is left.
+fn clean_name(name: &str) -> Option<String> {
+    let name: String = name
+        .trim()
+        .chars()
+        .filter(|c| !c.is_control())
+        .take(MAX_NAME_LENGTH)
+        .collect();
+    let name = name.trim_end();
+    if name.is_empty() {
+        None
+    } else {
+        Some(name.to_string())
+    }
+}
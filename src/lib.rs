@@ -1,7 +1,23 @@
-mod board;
+pub mod board;
+mod codec;
 mod display;
+pub mod engine;
+pub mod finesse;
 mod game;
-mod pieces;
+pub mod gamepad;
+pub mod garbage;
+pub mod input;
+pub mod keybinds;
+pub mod leaderboard;
+pub mod mode;
+pub mod pieces;
+pub mod random;
+pub mod replay;
+pub mod rotation;
+pub mod save;
+pub mod scoring;
+pub mod timestep;
+pub mod touch;
 mod utils;
 
 use wasm_bindgen::prelude::*;
@@ -0,0 +1,194 @@
+use std::cmp::Ordering;
+
+use wasm_bindgen::prelude::wasm_bindgen;
+
+use crate::{engine::Rules, leaderboard::HighScore};
+
+/// The kind of game being played. Each mode has its own high-score table.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum GameMode {
+    /// Clear 150 lines, with the speed capped at level 15.
+    Marathon,
+    /// Play until topping out, speeding up all the way to 20G.
+    Endless,
+    /// Clear 20 lines as fast as possible.
+    Sprint20,
+    /// Clear 40 lines as fast as possible.
+    Sprint40,
+    /// Clear 100 lines as fast as possible.
+    Sprint100,
+    /// Score as much as possible in two minutes.
+    Ultra2,
+    /// Score as much as possible in three minutes.
+    Ultra3,
+    /// Clear 10 rows of garbage as fast as possible.
+    Dig,
+    /// Clear 10 rows of garbage while more rises from below.
+    DigRising,
+}
+
+impl GameMode {
+    pub const ALL: [GameMode; 9] = [
+        GameMode::Marathon,
+        GameMode::Endless,
+        GameMode::Sprint20,
+        GameMode::Sprint40,
+        GameMode::Sprint100,
+        GameMode::Ultra2,
+        GameMode::Ultra3,
+        GameMode::Dig,
+        GameMode::DigRising,
+    ];
+
+    /// The stable name used in stored high scores.
+    pub fn name(self) -> &'static str {
+        match self {
+            GameMode::Marathon => "marathon",
+            // Endless was the only mode before Marathon was added, as "normal".
+            GameMode::Endless => "normal",
+            GameMode::Sprint20 => "sprint20",
+            GameMode::Sprint40 => "sprint40",
+            GameMode::Sprint100 => "sprint100",
+            GameMode::Ultra2 => "ultra2",
+            GameMode::Ultra3 => "ultra3",
+            GameMode::Dig => "dig",
+            GameMode::DigRising => "dig_rising",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<GameMode> {
+        GameMode::ALL
+            .iter()
+            .copied()
+            .find(|mode| mode.name() == name)
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            GameMode::Marathon => "MARATHON",
+            GameMode::Endless => "ENDLESS",
+            GameMode::Sprint20 => "SPRINT 20",
+            GameMode::Sprint40 => "SPRINT 40",
+            GameMode::Sprint100 => "SPRINT 100",
+            GameMode::Ultra2 => "ULTRA 2:00",
+            GameMode::Ultra3 => "ULTRA 3:00",
+            GameMode::Dig => "DIG",
+            GameMode::DigRising => "DIG RISING",
+        }
+    }
+
+    /// The lines that finish the game, if it has a goal.
+    pub fn line_goal(self) -> Option<u32> {
+        match self {
+            GameMode::Marathon => Some(150),
+            GameMode::Endless
+            | GameMode::Ultra2
+            | GameMode::Ultra3
+            | GameMode::Dig
+            | GameMode::DigRising => None,
+            GameMode::Sprint20 => Some(20),
+            GameMode::Sprint40 => Some(40),
+            GameMode::Sprint100 => Some(100),
+        }
+    }
+
+    /// How long the game lasts, if it is timed.
+    pub fn time_limit_ms(self) -> Option<f64> {
+        match self {
+            GameMode::Ultra2 => Some(120_000.0),
+            GameMode::Ultra3 => Some(180_000.0),
+            GameMode::Marathon
+            | GameMode::Endless
+            | GameMode::Sprint20
+            | GameMode::Sprint40
+            | GameMode::Sprint100
+            | GameMode::Dig
+            | GameMode::DigRising => None,
+        }
+    }
+
+    /// The level at which the speed stops rising, if it is capped.
+    pub fn max_level(self) -> Option<u32> {
+        match self {
+            GameMode::Marathon => Some(15),
+            GameMode::Endless
+            | GameMode::Sprint20
+            | GameMode::Sprint40
+            | GameMode::Sprint100
+            | GameMode::Ultra2
+            | GameMode::Ultra3
+            | GameMode::Dig
+            | GameMode::DigRising => None,
+        }
+    }
+
+    /// Rows of garbage the board starts with.
+    pub fn garbage_rows(self) -> u32 {
+        match self {
+            GameMode::Dig | GameMode::DigRising => 10,
+            GameMode::Marathon
+            | GameMode::Endless
+            | GameMode::Sprint20
+            | GameMode::Sprint40
+            | GameMode::Sprint100
+            | GameMode::Ultra2
+            | GameMode::Ultra3 => 0,
+        }
+    }
+
+    /// How often another garbage row rises, if it does.
+    pub fn garbage_interval_ms(self) -> Option<f64> {
+        match self {
+            GameMode::DigRising => Some(5000.0),
+            GameMode::Marathon
+            | GameMode::Endless
+            | GameMode::Sprint20
+            | GameMode::Sprint40
+            | GameMode::Sprint100
+            | GameMode::Ultra2
+            | GameMode::Ultra3
+            | GameMode::Dig => None,
+        }
+    }
+
+    /// `rules` with this mode's goal, level cap, garbage and time limit.
+    pub fn rules(self, rules: Rules) -> Rules {
+        Rules {
+            line_goal: self.line_goal(),
+            max_level: self.max_level(),
+            garbage_rows: self.garbage_rows(),
+            garbage_interval_ms: self.garbage_interval_ms(),
+            time_limit_ms: self.time_limit_ms(),
+            ..rules
+        }
+    }
+
+    /// Whether results are ranked by time rather than score.
+    pub fn ranks_by_time(self) -> bool {
+        matches!(
+            self,
+            GameMode::Sprint20
+                | GameMode::Sprint40
+                | GameMode::Sprint100
+                | GameMode::Dig
+                | GameMode::DigRising
+        )
+    }
+
+    /// Orders results best first.
+    pub fn compare(self, a: &HighScore, b: &HighScore) -> Ordering {
+        match self {
+            GameMode::Marathon | GameMode::Endless | GameMode::Ultra2 | GameMode::Ultra3 => b
+                .score
+                .cmp(&a.score)
+                .then(b.lines.cmp(&a.lines))
+                .then(a.time_ms.total_cmp(&b.time_ms)),
+            GameMode::Sprint20
+            | GameMode::Sprint40
+            | GameMode::Sprint100
+            | GameMode::Dig
+            | GameMode::DigRising => a.time_ms.total_cmp(&b.time_ms),
+        }
+    }
+}
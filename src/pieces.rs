@@ -5,7 +5,7 @@ pub struct PieceRotation {
     pub(crate) rows: Vec<Vec<i8>>,
 }
 
-#[derive(Clone, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum PieceType {
     Straight,
     LLeft,
@@ -16,11 +16,24 @@ pub enum PieceType {
     T,
 }
 
+impl PieceType {
+    pub const ALL: [PieceType; 7] = [
+        PieceType::Straight,
+        PieceType::LLeft,
+        PieceType::LRight,
+        PieceType::Square,
+        PieceType::S,
+        PieceType::Z,
+        PieceType::T,
+    ];
+}
+
 #[derive(Clone)]
 pub struct Piece {
     pub(crate) rotations: Vec<PieceRotation>,
 }
 
+#[derive(Clone)]
 pub struct PieceState {
     pub(crate) piece: Piece,
     pub(crate) piece_type: PieceType,
@@ -29,147 +42,79 @@ pub struct PieceState {
     pub(crate) rotation: u8,
 }
 
-impl Clone for PieceState {
-    fn clone(&self) -> Self {
-        PieceState {
-            piece: Piece::get_piece_data(&self.piece_type),
-            piece_type: self.piece_type.clone(),
-            row: self.row,
-            col: self.col,
-            rotation: self.rotation,
-        }
-    }
-}
-
 impl Piece {
     pub fn new(rotations: Vec<PieceRotation>) -> Piece {
         Piece { rotations }
     }
 
-    pub fn get_piece_data(piece_type: &PieceType) -> Piece {
-        Piece::new(match piece_type {
-            PieceType::Straight => vec![
-                PieceRotation {
-                    trans_row: 1,
-                    trans_col: 0,
-                    rows: vec![vec![1, 1, 1, 1]],
-                },
-                PieceRotation {
-                    trans_row: -1,
-                    trans_col: 1,
-                    rows: vec![vec![1], vec![1], vec![1], vec![1]],
-                },
-            ],
-            PieceType::LLeft => vec![
-                PieceRotation {
-                    trans_row: 1,
-                    trans_col: -1,
-                    rows: vec![vec![1, 1, 1], vec![0, 0, 1]],
-                },
-                PieceRotation {
-                    trans_row: -1,
-                    trans_col: 0,
-                    rows: vec![vec![0, 1], vec![0, 1], vec![1, 1]],
-                },
-                PieceRotation {
+    /// Builds a piece from explicitly listed rotation states, in clockwise
+    /// order starting from the spawn state.
+    pub fn from_states(trans_col: i8, states: Vec<Vec<Vec<i8>>>) -> Piece {
+        Piece::new(
+            states
+                .into_iter()
+                .map(|rows| PieceRotation {
                     trans_row: 0,
-                    trans_col: 0,
-                    rows: vec![vec![1, 0, 0], vec![1, 1, 1]],
-                },
-                PieceRotation {
-                    trans_row: 0,
-                    trans_col: 1,
-                    rows: vec![vec![1, 1], vec![1, 0], vec![1, 0]],
-                },
-            ],
-            PieceType::LRight => vec![
-                PieceRotation {
-                    trans_row: 1,
-                    trans_col: -1,
-                    rows: vec![vec![1, 1, 1], vec![1, 0, 0]],
-                },
-                PieceRotation {
-                    trans_row: -1,
-                    trans_col: 0,
-                    rows: vec![vec![1, 1], vec![0, 1], vec![0, 1]],
-                },
-                PieceRotation {
-                    trans_row: 0,
-                    trans_col: 0,
-                    rows: vec![vec![0, 0, 1], vec![1, 1, 1]],
-                },
-                PieceRotation {
-                    trans_row: 0,
-                    trans_col: 1,
-                    rows: vec![vec![1, 0], vec![1, 0], vec![1, 1]],
-                },
-            ],
-            PieceType::Square => vec![PieceRotation {
+                    trans_col,
+                    rows,
+                })
+                .collect(),
+        )
+    }
+
+    /// Builds all four rotation states by turning the spawn state's bounding
+    /// box clockwise about its centre.
+    pub fn from_spawn_state(trans_col: i8, spawn: Vec<Vec<i8>>) -> Piece {
+        let mut rotations = vec![PieceRotation {
+            trans_row: 0,
+            trans_col,
+            rows: spawn,
+        }];
+
+        for _ in 1..4 {
+            let previous = &rotations[rotations.len() - 1].rows;
+            let size = previous.len();
+            let rows = (0..size)
+                .map(|r| (0..size).map(|c| previous[size - 1 - c][r]).collect())
+                .collect();
+            rotations.push(PieceRotation {
                 trans_row: 0,
-                trans_col: 0,
-                rows: vec![vec![1, 1], vec![1, 1]],
-            }],
-            PieceType::S => vec![
-                PieceRotation {
-                    trans_row: 1,
-                    trans_col: 0,
-                    rows: vec![vec![0, 1, 1], vec![1, 1, 0]],
-                },
-                PieceRotation {
-                    trans_row: -1,
-                    trans_col: 0,
-                    rows: vec![vec![1, 0], vec![1, 1], vec![0, 1]],
-                },
-            ],
-            PieceType::Z => vec![
-                PieceRotation {
-                    trans_row: 1,
-                    trans_col: 0,
-                    rows: vec![vec![1, 1, 0], vec![0, 1, 1]],
-                },
-                PieceRotation {
-                    trans_row: -1,
-                    trans_col: 0,
-                    rows: vec![vec![0, 1], vec![1, 1], vec![1, 0]],
-                },
-            ],
-            PieceType::T => vec![
-                PieceRotation {
-                    trans_row: 1,
-                    trans_col: -1,
-                    rows: vec![vec![1, 1, 1], vec![0, 1, 0]],
-                },
-                PieceRotation {
-                    trans_row: -1,
-                    trans_col: 0,
-                    rows: vec![vec![0, 1], vec![1, 1], vec![0, 1]],
-                },
-                PieceRotation {
-                    trans_row: 0,
-                    trans_col: 0,
-                    rows: vec![vec![0, 1, 0], vec![1, 1, 1]],
-                },
-                PieceRotation {
-                    trans_row: 0,
-                    trans_col: 1,
-                    rows: vec![vec![1, 0], vec![1, 1], vec![1, 0]],
-                },
-            ],
-        })
+                trans_col,
+                rows,
+            });
+        }
+
+        Piece::new(rotations)
     }
 }
 
 impl PieceState {
-    pub fn new(piece_type: PieceType, column: i8) -> PieceState {
+    pub fn new(piece_type: PieceType, piece: Piece, column: i8) -> PieceState {
         PieceState {
-            piece: Piece::get_piece_data(&piece_type),
+            piece,
             piece_type,
-            row: -2,
+            row: -1,
             col: column,
             rotation: 0,
         }
     }
 
+    pub fn piece_type(&self) -> &PieceType {
+        &self.piece_type
+    }
+
+    pub fn row(&self) -> i8 {
+        self.row
+    }
+
+    pub fn col(&self) -> i8 {
+        self.col
+    }
+
+    pub fn rotation(&self) -> u8 {
+        self.rotation
+    }
+
     pub fn iter_blocks(&self) -> impl Iterator<Item = (i8, i8)> + '_ {
         let rotation = &self.piece.rotations[self.rotation as usize];
         rotation.rows.iter().enumerate().flat_map(move |(r, row)| {
@@ -201,6 +146,11 @@ impl PieceState {
         self.rotation = (self.rotation + 1) % self.piece.rotations.len() as u8;
     }
 
+    pub fn rotate_counter_clockwise(&mut self) {
+        let states = self.piece.rotations.len() as u8;
+        self.rotation = (self.rotation + states - 1) % states;
+    }
+
     pub fn move_down(&self) -> PieceState {
         let mut next_piece = self.clone();
         next_piece.row += 1;
@@ -0,0 +1,206 @@
+use wasm_bindgen::prelude::wasm_bindgen;
+
+use crate::pieces::PieceType;
+
+/// A small seedable PRNG (SplitMix64), so piece sequences are reproducible
+/// and don't depend on the browser.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u32) -> Rng {
+        Rng { state: seed as u64 }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// A value in `[0, 1)`.
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// A value in `0..bound`.
+    pub fn below(&mut self, bound: usize) -> usize {
+        (self.next_f64() * bound as f64) as usize
+    }
+}
+
+/// Decides which piece comes next. Implementations keep their own history
+/// and draw all their randomness from the engine's `Rng`.
+pub trait Randomizer {
+    fn kind(&self) -> RandomizerKind;
+
+    fn next(&mut self, rng: &mut Rng) -> PieceType;
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RandomizerKind {
+    SevenBag,
+    FourteenBag,
+    PureRandom,
+    Nes,
+    TgmHistory,
+}
+
+impl RandomizerKind {
+    pub fn build(self) -> Box<dyn Randomizer> {
+        match self {
+            RandomizerKind::SevenBag => Box::new(Bag::new(1)),
+            RandomizerKind::FourteenBag => Box::new(Bag::new(2)),
+            RandomizerKind::PureRandom => Box::new(PureRandom),
+            RandomizerKind::Nes => Box::new(NesRandomizer::new()),
+            RandomizerKind::TgmHistory => Box::new(TgmHistory::new()),
+        }
+    }
+}
+
+/// Deals shuffled bags holding `copies` of every piece.
+pub struct Bag {
+    copies: usize,
+    pieces: Vec<PieceType>,
+}
+
+impl Bag {
+    pub fn new(copies: usize) -> Bag {
+        Bag {
+            copies,
+            pieces: Vec::new(),
+        }
+    }
+}
+
+impl Randomizer for Bag {
+    fn kind(&self) -> RandomizerKind {
+        if self.copies == 2 {
+            RandomizerKind::FourteenBag
+        } else {
+            RandomizerKind::SevenBag
+        }
+    }
+
+    fn next(&mut self, rng: &mut Rng) -> PieceType {
+        if self.pieces.is_empty() {
+            for _ in 0..self.copies {
+                self.pieces.extend(PieceType::ALL.iter().cloned());
+            }
+
+            for i in (0..self.pieces.len()).rev() {
+                let j = rng.below(i + 1);
+                self.pieces.swap(i, j);
+            }
+        }
+        self.pieces.pop().expect("bag was just refilled")
+    }
+}
+
+/// Every piece is equally likely, every time.
+pub struct PureRandom;
+
+impl Randomizer for PureRandom {
+    fn kind(&self) -> RandomizerKind {
+        RandomizerKind::PureRandom
+    }
+
+    fn next(&mut self, rng: &mut Rng) -> PieceType {
+        PieceType::ALL[rng.below(PieceType::ALL.len())].clone()
+    }
+}
+
+/// The NES randomizer: roll one of eight outcomes, and roll once more among
+/// the seven pieces if that gave the previous piece or the spare outcome.
+pub struct NesRandomizer {
+    last: Option<PieceType>,
+}
+
+impl NesRandomizer {
+    pub fn new() -> NesRandomizer {
+        NesRandomizer { last: None }
+    }
+}
+
+impl Default for NesRandomizer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Randomizer for NesRandomizer {
+    fn kind(&self) -> RandomizerKind {
+        RandomizerKind::Nes
+    }
+
+    fn next(&mut self, rng: &mut Rng) -> PieceType {
+        let roll = rng.below(PieceType::ALL.len() + 1);
+        let piece = match PieceType::ALL.get(roll) {
+            Some(piece) if Some(piece) != self.last.as_ref() => piece.clone(),
+            _ => PieceType::ALL[rng.below(PieceType::ALL.len())].clone(),
+        };
+        self.last = Some(piece.clone());
+        piece
+    }
+}
+
+/// TGM's randomizer: reroll up to six times while the piece is among the
+/// last four dealt. The first piece is never S, Z or O.
+pub struct TgmHistory {
+    history: Vec<PieceType>,
+    first: bool,
+}
+
+const TGM_ROLLS: usize = 6;
+
+impl TgmHistory {
+    pub fn new() -> TgmHistory {
+        TgmHistory {
+            history: vec![PieceType::Z, PieceType::S, PieceType::S, PieceType::Z],
+            first: true,
+        }
+    }
+}
+
+impl Default for TgmHistory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Randomizer for TgmHistory {
+    fn kind(&self) -> RandomizerKind {
+        RandomizerKind::TgmHistory
+    }
+
+    fn next(&mut self, rng: &mut Rng) -> PieceType {
+        let piece = if self.first {
+            self.first = false;
+            let first_pieces = [
+                PieceType::Straight,
+                PieceType::LLeft,
+                PieceType::LRight,
+                PieceType::T,
+            ];
+            first_pieces[rng.below(first_pieces.len())].clone()
+        } else {
+            let mut piece = PieceType::ALL[rng.below(PieceType::ALL.len())].clone();
+            for _ in 1..TGM_ROLLS {
+                if !self.history.contains(&piece) {
+                    break;
+                }
+                piece = PieceType::ALL[rng.below(PieceType::ALL.len())].clone();
+            }
+            piece
+        };
+
+        self.history.remove(0);
+        self.history.push(piece.clone());
+        piece
+    }
+}
@@ -0,0 +1,381 @@
+use crate::{
+    codec::{
+        base64_decode, base64_encode, randomizer_code, randomizer_from_code, rotation_system_code,
+        rotation_system_from_code, write_f64, write_varint, Reader,
+    },
+    engine::{Engine, Rules},
+    input::{Direction, Handling, InputHandler},
+    keybinds::Action,
+    random::RandomizerKind,
+    rotation::RotationSystemKind,
+    scoring::ClearEvent,
+    timestep::{FixedTimestep, STEP_MS},
+};
+
+/// One change to the player's input.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReplayInput {
+    Press(Action),
+    Release(Action),
+    /// Every held input was dropped at once, e.g. because the game paused.
+    ReleaseAll,
+}
+
+/// An input that arrived after `frame` logic steps had run.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ReplayEvent {
+    pub frame: u64,
+    pub input: ReplayInput,
+}
+
+/// Everything needed to reproduce a game: its settings at the start and the
+/// inputs made during it. Settings changed mid-game are not captured.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Replay {
+    pub seed: u32,
+    pub randomizer: RandomizerKind,
+    pub rotation_system: RotationSystemKind,
+    pub rules: Rules,
+    pub handling: Handling,
+    pub events: Vec<ReplayEvent>,
+    /// How many logic steps the game ran for.
+    pub frames: u64,
+}
+
+impl Replay {
+    /// Starts an empty recording of a game about to be played on `engine`.
+    pub fn start(engine: &Engine, handling: Handling) -> Replay {
+        Replay {
+            seed: engine.seed(),
+            randomizer: engine.randomizer().kind(),
+            rotation_system: engine.rotation_system().kind(),
+            rules: engine.rules(),
+            handling,
+            events: Vec::new(),
+            frames: 0,
+        }
+    }
+
+    pub fn record(&mut self, frame: u64, input: ReplayInput) {
+        self.events.push(ReplayEvent { frame, input });
+        self.frames = self.frames.max(frame);
+    }
+
+    /// Builds the engine the recorded game started with.
+    pub fn new_engine(&self) -> Engine {
+        Engine::with_rules(
+            self.seed,
+            self.randomizer.build(),
+            self.rotation_system.build(),
+            self.rules,
+        )
+    }
+}
+
+/// Applies `input` the same way for live play and for playback, so both
+/// produce the same game.
+pub fn apply_input(engine: &mut Engine, input_handler: &mut InputHandler, input: ReplayInput) {
+    match input {
+        ReplayInput::Press(action) => match action {
+            Action::MoveLeft => input_handler.press(engine, Direction::Left),
+            Action::MoveRight => input_handler.press(engine, Direction::Right),
+            Action::SoftDrop => input_handler.press_soft_drop(engine),
+            Action::HardDrop => engine.hard_drop(),
+            Action::RotateClockwise => {
+                engine.rotate_clockwise();
+            }
+            Action::RotateCounterClockwise => {
+                engine.rotate_counter_clockwise();
+            }
+            Action::Rotate180 => {
+                engine.rotate_180();
+            }
+            Action::Hold => engine.hold(),
+            Action::Pause => {}
+        },
+        ReplayInput::Release(action) => match action {
+            Action::MoveLeft => input_handler.release(engine, Direction::Left),
+            Action::MoveRight => input_handler.release(engine, Direction::Right),
+            Action::SoftDrop => input_handler.release_soft_drop(),
+            _ => {}
+        },
+        ReplayInput::ReleaseAll => input_handler.release_all(),
+    }
+}
+
+/// Runs a `Replay` on its own engine, at any speed, from any frame.
+pub struct ReplayPlayer {
+    replay: Replay,
+    engine: Engine,
+    input: InputHandler,
+    timestep: FixedTimestep,
+    frame: u64,
+    next_event: usize,
+    speed: f64,
+}
+
+impl ReplayPlayer {
+    pub fn new(replay: Replay) -> ReplayPlayer {
+        ReplayPlayer {
+            engine: replay.new_engine(),
+            input: InputHandler::new(replay.handling),
+            timestep: FixedTimestep::new(),
+            frame: 0,
+            next_event: 0,
+            speed: 1.0,
+            replay,
+        }
+    }
+
+    pub fn replay(&self) -> &Replay {
+        &self.replay
+    }
+
+    pub fn engine(&self) -> &Engine {
+        &self.engine
+    }
+
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
+
+    pub fn speed(&self) -> f64 {
+        self.speed
+    }
+
+    /// Sets the playback rate; `0.0` holds the current frame.
+    pub fn set_speed(&mut self, speed: f64) {
+        self.speed = speed.max(0.0);
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.frame >= self.replay.frames || self.engine.is_game_over()
+    }
+
+    /// Plays `delta_time` of real time, scaled by the speed, and returns the
+    /// clears made meanwhile, oldest first.
+    pub fn advance(&mut self, delta_time: f64) -> Vec<ClearEvent> {
+        let mut clear_events = Vec::new();
+        let steps = self.timestep.advance(delta_time * self.speed);
+        for _ in 0..steps {
+            if self.is_finished() {
+                break;
+            }
+            clear_events.extend(self.step());
+        }
+        clear_events
+    }
+
+    /// Runs a single logic step, applying the inputs recorded before it, and
+    /// returns the clears it made.
+    pub fn step(&mut self) -> Vec<ClearEvent> {
+        while let Some(event) = self.replay.events.get(self.next_event) {
+            if event.frame > self.frame {
+                break;
+            }
+            apply_input(&mut self.engine, &mut self.input, event.input);
+            self.next_event += 1;
+        }
+
+        self.input.update(&mut self.engine, STEP_MS);
+        self.engine.tick(STEP_MS);
+        self.frame += 1;
+        self.engine.take_clear_events()
+    }
+
+    /// Jumps to `frame`. Seeking backwards replays from the start, as the
+    /// engine can't run in reverse.
+    pub fn seek(&mut self, frame: u64) {
+        if frame < self.frame {
+            let speed = self.speed;
+            *self = ReplayPlayer::new(self.replay.clone());
+            self.speed = speed;
+        }
+        while self.frame < frame && !self.is_finished() {
+            self.step();
+        }
+    }
+}
+
+/// Identifies a replay file, followed by the format version.
+const MAGIC: &[u8; 3] = b"TRP";
+
+/// The format written by `Replay::to_bytes`. Decoders keep accepting every
+/// earlier version, so bump this whenever the layout changes.
+pub const REPLAY_FORMAT_VERSION: u8 = 1;
+
+/// Bits of an event word that hold the input; the rest is the frame delta.
+const INPUT_BITS: u32 = 6;
+const PRESS: u8 = 0x00;
+const RELEASE: u8 = 0x10;
+const RELEASE_ALL: u8 = 0x20;
+
+impl Replay {
+    /// Encodes the replay in the version 1 binary format:
+    ///
+    /// | Field                                | Encoding           |
+    /// |--------------------------------------|--------------------|
+    /// | magic `TRP`, then format version     | 3 bytes, `u8`      |
+    /// | seed                                 | `u32` LE           |
+    /// | randomizer, rotation system          | `u8` each          |
+    /// | lock delay (ms)                      | `f64` LE           |
+    /// | max lock resets, start level, preview| varint each        |
+    /// | line goal + 1, or 0 for none         | varint             |
+    /// | max level + 1, or 0 for none         | varint             |
+    /// | starting garbage rows                | varint             |
+    /// | garbage interval (ms), 0 for none    | `f64` LE           |
+    /// | time limit (ms) + 1, or 0 for none   | varint             |
+    /// | DAS, ARR (ms), soft drop factor      | `f64` LE each      |
+    /// | length in frames, event count        | varint each        |
+    /// | events                               | varint each        |
+    ///
+    /// Varints are unsigned LEB128. Each event is one varint holding the
+    /// frames since the previous event shifted left by six bits, with the
+    /// input in the low bits: `0x00 | action` for a press, `0x10 | action`
+    /// for a release and `0x20` for releasing everything. Actions are
+    /// numbered by their position in `Action::ALL`.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(REPLAY_FORMAT_VERSION);
+
+        bytes.extend_from_slice(&self.seed.to_le_bytes());
+        bytes.push(randomizer_code(self.randomizer));
+        bytes.push(rotation_system_code(self.rotation_system));
+        write_f64(&mut bytes, self.rules.lock_delay_ms);
+        write_varint(&mut bytes, u64::from(self.rules.max_lock_resets));
+        write_varint(&mut bytes, u64::from(self.rules.start_level));
+        write_varint(&mut bytes, self.rules.preview_count as u64);
+        write_varint(
+            &mut bytes,
+            self.rules.line_goal.map_or(0, |goal| u64::from(goal) + 1),
+        );
+        write_varint(
+            &mut bytes,
+            self.rules.max_level.map_or(0, |level| u64::from(level) + 1),
+        );
+        write_varint(&mut bytes, u64::from(self.rules.garbage_rows));
+        write_f64(&mut bytes, self.rules.garbage_interval_ms.unwrap_or(0.0));
+        write_varint(
+            &mut bytes,
+            self.rules
+                .time_limit_ms
+                .map_or(0, |limit| limit.round() as u64 + 1),
+        );
+        write_f64(&mut bytes, self.handling.das_ms);
+        write_f64(&mut bytes, self.handling.arr_ms);
+        write_f64(&mut bytes, self.handling.soft_drop_factor);
+        write_varint(&mut bytes, self.frames);
+
+        write_varint(&mut bytes, self.events.len() as u64);
+        let mut last_frame = 0;
+        for event in self.events.iter() {
+            let delta = event.frame.saturating_sub(last_frame);
+            last_frame = event.frame;
+            write_varint(
+                &mut bytes,
+                delta << INPUT_BITS | u64::from(input_code(event.input)),
+            );
+        }
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Replay, String> {
+        let mut reader = Reader::new(bytes);
+        if reader.take(MAGIC.len())? != MAGIC {
+            return Err("Not a replay".to_string());
+        }
+        let version = reader.u8()?;
+        if version == 0 || version > REPLAY_FORMAT_VERSION {
+            return Err(format!("Unsupported replay version {}", version));
+        }
+
+        let seed = u32::from_le_bytes(reader.array()?);
+        let randomizer = randomizer_from_code(reader.u8()?)?;
+        let rotation_system = rotation_system_from_code(reader.u8()?)?;
+        let rules = Rules {
+            lock_delay_ms: reader.f64()?,
+            max_lock_resets: reader.varint_u32()?,
+            start_level: reader.varint_u32()?,
+            preview_count: reader.varint()? as usize,
+            line_goal: reader.varint_u32()?.checked_sub(1),
+            max_level: reader.varint_u32()?.checked_sub(1),
+            garbage_rows: reader.varint_u32()?,
+            garbage_interval_ms: Some(reader.f64()?).filter(|&interval| interval > 0.0),
+            time_limit_ms: reader.varint()?.checked_sub(1).map(|limit| limit as f64),
+        };
+        rules.validate()?;
+        let handling = Handling {
+            das_ms: reader.f64()?,
+            arr_ms: reader.f64()?,
+            soft_drop_factor: reader.f64()?,
+        };
+        let frames = reader.varint()?;
+
+        let event_count = reader.varint()?;
+        let mut events = Vec::new();
+        let mut frame: u64 = 0;
+        for _ in 0..event_count {
+            let word = reader.varint()?;
+            frame = frame
+                .checked_add(word >> INPUT_BITS)
+                .ok_or("Replay event frame overflows")?;
+            let input = input_from_code((word & ((1 << INPUT_BITS) - 1)) as u8)?;
+            events.push(ReplayEvent { frame, input });
+        }
+
+        if !reader.is_empty() {
+            return Err("Unexpected data after the replay".to_string());
+        }
+
+        Ok(Replay {
+            seed,
+            randomizer,
+            rotation_system,
+            rules,
+            handling,
+            events,
+            frames,
+        })
+    }
+
+    /// The binary format as URL-safe base64 without padding, short enough to
+    /// paste into a link or a chat message.
+    pub fn to_base64(&self) -> String {
+        base64_encode(&self.to_bytes())
+    }
+
+    pub fn from_base64(text: &str) -> Result<Replay, String> {
+        Replay::from_bytes(&base64_decode(text.trim())?)
+    }
+}
+
+fn action_code(action: Action) -> u8 {
+    Action::ALL
+        .iter()
+        .position(|&other| other == action)
+        .expect("Expected every action to be in `Action::ALL`") as u8
+}
+
+fn input_code(input: ReplayInput) -> u8 {
+    match input {
+        ReplayInput::Press(action) => PRESS | action_code(action),
+        ReplayInput::Release(action) => RELEASE | action_code(action),
+        ReplayInput::ReleaseAll => RELEASE_ALL,
+    }
+}
+
+fn input_from_code(code: u8) -> Result<ReplayInput, String> {
+    let action = || {
+        Action::ALL
+            .get(usize::from(code & 0x0f))
+            .copied()
+            .ok_or_else(|| format!("Unknown replay input {:#04x}", code))
+    };
+    match code & 0x30 {
+        PRESS => Ok(ReplayInput::Press(action()?)),
+        RELEASE => Ok(ReplayInput::Release(action()?)),
+        RELEASE_ALL if code == RELEASE_ALL => Ok(ReplayInput::ReleaseAll),
+        _ => Err(format!("Unknown replay input {:#04x}", code)),
+    }
+}
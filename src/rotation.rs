@@ -0,0 +1,301 @@
+use wasm_bindgen::prelude::wasm_bindgen;
+
+use crate::{
+    board::Board,
+    pieces::{Piece, PieceState, PieceType},
+};
+
+/// Supplies the rotation states of every piece and the offsets to try when a
+/// rotation is blocked.
+pub trait RotationSystem {
+    fn kind(&self) -> RotationSystemKind;
+
+    fn piece(&self, piece_type: &PieceType) -> Piece;
+
+    /// The `(col, row)` offsets to try, in order, when rotating `piece_type`
+    /// out of rotation state `from`. Rows grow downwards.
+    fn kicks(&self, piece_type: &PieceType, from: u8, clockwise: bool) -> &'static [(i8, i8)];
+
+    /// Offsets for a half turn. None of the shipped systems define 180
+    /// kicks, so by default the piece only turns in place.
+    fn kicks_180(&self, _piece_type: &PieceType, _from: u8) -> &'static [(i8, i8)] {
+        &NO_KICKS
+    }
+
+    /// Whether a blocked rotation into `rotated` may try the kicks beyond the
+    /// first offset at all.
+    fn can_kick(&self, _board: &Board, _rotated: &PieceState) -> bool {
+        true
+    }
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RotationSystemKind {
+    Srs,
+    Ars,
+    Nes,
+}
+
+impl RotationSystemKind {
+    pub fn build(self) -> Box<dyn RotationSystem> {
+        match self {
+            RotationSystemKind::Srs => Box::new(Srs),
+            RotationSystemKind::Ars => Box::new(Ars),
+            RotationSystemKind::Nes => Box::new(Nes),
+        }
+    }
+}
+
+const NO_KICKS: [(i8, i8); 1] = [(0, 0)];
+
+/// Super Rotation System wall kicks, indexed by the rotation state being
+/// left, with 0 = spawn, 1 = R, 2 = 180 and 3 = L.
+const JLSTZ_CLOCKWISE_KICKS: [[(i8, i8); 5]; 4] = [
+    [(0, 0), (-1, 0), (-1, -1), (0, 2), (-1, 2)],
+    [(0, 0), (1, 0), (1, 1), (0, -2), (1, -2)],
+    [(0, 0), (1, 0), (1, -1), (0, 2), (1, 2)],
+    [(0, 0), (-1, 0), (-1, 1), (0, -2), (-1, -2)],
+];
+
+const JLSTZ_COUNTER_CLOCKWISE_KICKS: [[(i8, i8); 5]; 4] = [
+    [(0, 0), (1, 0), (1, -1), (0, 2), (1, 2)],
+    [(0, 0), (1, 0), (1, 1), (0, -2), (1, -2)],
+    [(0, 0), (-1, 0), (-1, -1), (0, 2), (-1, 2)],
+    [(0, 0), (-1, 0), (-1, 1), (0, -2), (-1, -2)],
+];
+
+const I_CLOCKWISE_KICKS: [[(i8, i8); 5]; 4] = [
+    [(0, 0), (-2, 0), (1, 0), (-2, 1), (1, -2)],
+    [(0, 0), (-1, 0), (2, 0), (-1, -2), (2, 1)],
+    [(0, 0), (2, 0), (-1, 0), (2, -1), (-1, 2)],
+    [(0, 0), (1, 0), (-2, 0), (1, 2), (-2, -1)],
+];
+
+const I_COUNTER_CLOCKWISE_KICKS: [[(i8, i8); 5]; 4] = [
+    [(0, 0), (-1, 0), (2, 0), (-1, -2), (2, 1)],
+    [(0, 0), (2, 0), (-1, 0), (2, -1), (-1, 2)],
+    [(0, 0), (1, 0), (-2, 0), (1, 2), (-2, -1)],
+    [(0, 0), (-2, 0), (1, 0), (-2, 1), (1, -2)],
+];
+
+/// The guideline Super Rotation System.
+pub struct Srs;
+
+impl RotationSystem for Srs {
+    fn kind(&self) -> RotationSystemKind {
+        RotationSystemKind::Srs
+    }
+
+    fn piece(&self, piece_type: &PieceType) -> Piece {
+        match piece_type {
+            PieceType::Straight => Piece::from_spawn_state(
+                0,
+                vec![
+                    vec![0, 0, 0, 0],
+                    vec![1, 1, 1, 1],
+                    vec![0, 0, 0, 0],
+                    vec![0, 0, 0, 0],
+                ],
+            ),
+            PieceType::LLeft => {
+                Piece::from_spawn_state(0, vec![vec![1, 0, 0], vec![1, 1, 1], vec![0, 0, 0]])
+            }
+            PieceType::LRight => {
+                Piece::from_spawn_state(0, vec![vec![0, 0, 1], vec![1, 1, 1], vec![0, 0, 0]])
+            }
+            PieceType::Square => Piece::from_states(1, vec![vec![vec![1, 1], vec![1, 1]]]),
+            PieceType::S => {
+                Piece::from_spawn_state(0, vec![vec![0, 1, 1], vec![1, 1, 0], vec![0, 0, 0]])
+            }
+            PieceType::Z => {
+                Piece::from_spawn_state(0, vec![vec![1, 1, 0], vec![0, 1, 1], vec![0, 0, 0]])
+            }
+            PieceType::T => {
+                Piece::from_spawn_state(0, vec![vec![0, 1, 0], vec![1, 1, 1], vec![0, 0, 0]])
+            }
+        }
+    }
+
+    fn kicks(&self, piece_type: &PieceType, from: u8, clockwise: bool) -> &'static [(i8, i8)] {
+        let from = from as usize % 4;
+        match (piece_type, clockwise) {
+            (PieceType::Square, _) => &NO_KICKS,
+            (PieceType::Straight, true) => &I_CLOCKWISE_KICKS[from],
+            (PieceType::Straight, false) => &I_COUNTER_CLOCKWISE_KICKS[from],
+            (_, true) => &JLSTZ_CLOCKWISE_KICKS[from],
+            (_, false) => &JLSTZ_COUNTER_CLOCKWISE_KICKS[from],
+        }
+    }
+}
+
+const ARS_KICKS: [(i8, i8); 3] = [(0, 0), (1, 0), (-1, 0)];
+
+/// Arika's rotation system from the TGM series: bottom-aligned states, a
+/// one-column kick to the right then left, and no kicks for the I piece.
+pub struct Ars;
+
+impl RotationSystem for Ars {
+    fn kind(&self) -> RotationSystemKind {
+        RotationSystemKind::Ars
+    }
+
+    fn piece(&self, piece_type: &PieceType) -> Piece {
+        match piece_type {
+            PieceType::Straight => Piece::from_states(
+                0,
+                vec![
+                    vec![
+                        vec![0, 0, 0, 0],
+                        vec![1, 1, 1, 1],
+                        vec![0, 0, 0, 0],
+                        vec![0, 0, 0, 0],
+                    ],
+                    vec![
+                        vec![0, 0, 1, 0],
+                        vec![0, 0, 1, 0],
+                        vec![0, 0, 1, 0],
+                        vec![0, 0, 1, 0],
+                    ],
+                ],
+            ),
+            PieceType::LLeft => Piece::from_states(
+                0,
+                vec![
+                    vec![vec![0, 0, 0], vec![1, 1, 1], vec![0, 0, 1]],
+                    vec![vec![0, 1, 0], vec![0, 1, 0], vec![1, 1, 0]],
+                    vec![vec![0, 0, 0], vec![1, 0, 0], vec![1, 1, 1]],
+                    vec![vec![0, 1, 1], vec![0, 1, 0], vec![0, 1, 0]],
+                ],
+            ),
+            PieceType::LRight => Piece::from_states(
+                0,
+                vec![
+                    vec![vec![0, 0, 0], vec![1, 1, 1], vec![1, 0, 0]],
+                    vec![vec![1, 1, 0], vec![0, 1, 0], vec![0, 1, 0]],
+                    vec![vec![0, 0, 0], vec![0, 0, 1], vec![1, 1, 1]],
+                    vec![vec![0, 1, 0], vec![0, 1, 0], vec![0, 1, 1]],
+                ],
+            ),
+            PieceType::Square => {
+                Piece::from_states(1, vec![vec![vec![0, 0], vec![1, 1], vec![1, 1]]])
+            }
+            PieceType::S => Piece::from_states(
+                0,
+                vec![
+                    vec![vec![0, 0, 0], vec![0, 1, 1], vec![1, 1, 0]],
+                    vec![vec![1, 0, 0], vec![1, 1, 0], vec![0, 1, 0]],
+                ],
+            ),
+            PieceType::Z => Piece::from_states(
+                0,
+                vec![
+                    vec![vec![0, 0, 0], vec![1, 1, 0], vec![0, 1, 1]],
+                    vec![vec![0, 0, 1], vec![0, 1, 1], vec![0, 1, 0]],
+                ],
+            ),
+            PieceType::T => Piece::from_states(
+                0,
+                vec![
+                    vec![vec![0, 0, 0], vec![1, 1, 1], vec![0, 1, 0]],
+                    vec![vec![0, 1, 0], vec![1, 1, 0], vec![0, 1, 0]],
+                    vec![vec![0, 0, 0], vec![0, 1, 0], vec![1, 1, 1]],
+                    vec![vec![0, 1, 0], vec![0, 1, 1], vec![0, 1, 0]],
+                ],
+            ),
+        }
+    }
+
+    fn kicks(&self, piece_type: &PieceType, _from: u8, _clockwise: bool) -> &'static [(i8, i8)] {
+        match piece_type {
+            PieceType::Straight | PieceType::Square => &NO_KICKS,
+            _ => &ARS_KICKS,
+        }
+    }
+
+    /// The centre-column rule: J, L and T may not kick when the first
+    /// blocked cell, reading the 3x3 box left to right and top to bottom,
+    /// is in the middle column.
+    fn can_kick(&self, board: &Board, rotated: &PieceState) -> bool {
+        if !matches!(
+            rotated.piece_type,
+            PieceType::LLeft | PieceType::LRight | PieceType::T
+        ) {
+            return true;
+        }
+
+        let mut blocked = rotated
+            .iter_blocks()
+            .filter(|&(r, c)| !board.is_open_cell(r, c))
+            .map(|(r, c)| (r - rotated.row, c - rotated.col))
+            .collect::<Vec<_>>();
+        blocked.sort();
+
+        match blocked.first() {
+            Some(&(_, c)) => c != 1,
+            None => true,
+        }
+    }
+}
+
+/// The right-handed system of the NES game: pieces turn about a fixed
+/// centre and never kick.
+pub struct Nes;
+
+impl RotationSystem for Nes {
+    fn kind(&self) -> RotationSystemKind {
+        RotationSystemKind::Nes
+    }
+
+    fn piece(&self, piece_type: &PieceType) -> Piece {
+        match piece_type {
+            PieceType::Straight => Piece::from_states(
+                0,
+                vec![
+                    vec![
+                        vec![0, 0, 0, 0],
+                        vec![0, 0, 0, 0],
+                        vec![1, 1, 1, 1],
+                        vec![0, 0, 0, 0],
+                    ],
+                    vec![
+                        vec![0, 0, 1, 0],
+                        vec![0, 0, 1, 0],
+                        vec![0, 0, 1, 0],
+                        vec![0, 0, 1, 0],
+                    ],
+                ],
+            ),
+            PieceType::LLeft => {
+                Piece::from_spawn_state(0, vec![vec![0, 0, 0], vec![1, 1, 1], vec![0, 0, 1]])
+            }
+            PieceType::LRight => {
+                Piece::from_spawn_state(0, vec![vec![0, 0, 0], vec![1, 1, 1], vec![1, 0, 0]])
+            }
+            PieceType::Square => {
+                Piece::from_states(1, vec![vec![vec![0, 0], vec![1, 1], vec![1, 1]]])
+            }
+            PieceType::S => Piece::from_states(
+                0,
+                vec![
+                    vec![vec![0, 0, 0], vec![0, 1, 1], vec![1, 1, 0]],
+                    vec![vec![0, 1, 0], vec![0, 1, 1], vec![0, 0, 1]],
+                ],
+            ),
+            PieceType::Z => Piece::from_states(
+                0,
+                vec![
+                    vec![vec![0, 0, 0], vec![1, 1, 0], vec![0, 1, 1]],
+                    vec![vec![0, 0, 1], vec![0, 1, 1], vec![0, 1, 0]],
+                ],
+            ),
+            PieceType::T => {
+                Piece::from_spawn_state(0, vec![vec![0, 0, 0], vec![1, 1, 1], vec![0, 1, 0]])
+            }
+        }
+    }
+
+    fn kicks(&self, _piece_type: &PieceType, _from: u8, _clockwise: bool) -> &'static [(i8, i8)] {
+        &NO_KICKS
+    }
+}
@@ -0,0 +1,278 @@
+use crate::{
+    codec::{
+        base64_decode, base64_encode, randomizer_code, randomizer_from_code, rotation_system_code,
+        rotation_system_from_code, write_f64, write_piece_type, write_string, write_varint, Reader,
+    },
+    engine::{EngineSnapshot, Rules, Stats},
+    mode::GameMode,
+    replay::Replay,
+};
+
+const MAGIC: &[u8; 3] = b"TSV";
+
+/// The format written by `SavedGame::to_bytes`. Saves from other versions
+/// are rejected; they only need to survive a page reload.
+pub const SAVE_FORMAT_VERSION: u8 = 6;
+
+/// A game in progress: the engine, plus the recording so far so the replay
+/// still covers the whole game once it ends.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SavedGame {
+    pub mode: GameMode,
+    pub engine: EngineSnapshot,
+    pub replay: Replay,
+    /// Logic steps run before the save.
+    pub frame: u64,
+    /// Finesse faults made before the save.
+    pub finesse_faults: u32,
+}
+
+impl SavedGame {
+    /// Encodes the save as `TSV`, the format version, the mode's name, the
+    /// engine snapshot field by field and the replay as length-prefixed
+    /// `Replay::to_bytes`.
+    /// Board cells are indices into a table of the colours in use.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let engine = &self.engine;
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(SAVE_FORMAT_VERSION);
+        write_string(&mut bytes, self.mode.name());
+
+        bytes.extend_from_slice(&engine.seed.to_le_bytes());
+        bytes.push(randomizer_code(engine.randomizer));
+        bytes.push(rotation_system_code(engine.rotation_system));
+        write_varint(&mut bytes, engine.pieces_drawn);
+
+        write_f64(&mut bytes, engine.rules.lock_delay_ms);
+        write_varint(&mut bytes, u64::from(engine.rules.max_lock_resets));
+        write_varint(&mut bytes, u64::from(engine.rules.start_level));
+        write_varint(&mut bytes, engine.rules.preview_count as u64);
+        write_varint(
+            &mut bytes,
+            engine.rules.line_goal.map_or(0, |goal| u64::from(goal) + 1),
+        );
+        write_varint(
+            &mut bytes,
+            engine
+                .rules
+                .max_level
+                .map_or(0, |level| u64::from(level) + 1),
+        );
+        write_varint(&mut bytes, u64::from(engine.rules.garbage_rows));
+        write_f64(&mut bytes, engine.rules.garbage_interval_ms.unwrap_or(0.0));
+        write_f64(&mut bytes, engine.rules.time_limit_ms.unwrap_or(0.0));
+
+        for value in [
+            engine.stats.score,
+            engine.stats.lines,
+            engine.stats.level,
+            engine.stats.pieces,
+        ] {
+            write_varint(&mut bytes, u64::from(value));
+        }
+
+        let mut colors: Vec<&str> = Vec::new();
+        for color in engine.cells.iter().flatten().flatten() {
+            if !colors.contains(&color.as_str()) {
+                colors.push(color);
+            }
+        }
+        write_varint(&mut bytes, colors.len() as u64);
+        for color in colors.iter() {
+            write_string(&mut bytes, color);
+        }
+        write_varint(&mut bytes, engine.cells.len() as u64);
+        write_varint(
+            &mut bytes,
+            engine.cells.first().map_or(0, |row| row.len()) as u64,
+        );
+        for cell in engine.cells.iter().flatten() {
+            let code = match cell {
+                Some(color) => colors.iter().position(|&other| other == color).unwrap() + 1,
+                None => 0,
+            };
+            write_varint(&mut bytes, code as u64);
+        }
+
+        write_piece_type(&mut bytes, &engine.piece_type);
+        bytes.push(engine.piece_row as u8);
+        bytes.push(engine.piece_col as u8);
+        bytes.push(engine.piece_rotation);
+
+        bytes.push(engine.held_piece.is_some() as u8);
+        if let Some(held_piece) = &engine.held_piece {
+            write_piece_type(&mut bytes, held_piece);
+        }
+        bytes.push(engine.can_hold as u8);
+        write_varint(&mut bytes, engine.next_queue.len() as u64);
+        for piece_type in engine.next_queue.iter() {
+            write_piece_type(&mut bytes, piece_type);
+        }
+
+        write_f64(&mut bytes, engine.time_since_last_drop);
+        write_f64(&mut bytes, engine.lock_timer);
+        write_varint(&mut bytes, u64::from(engine.lock_resets));
+        bytes.push(engine.lowest_row as u8);
+        write_varint(
+            &mut bytes,
+            engine.last_kick.map_or(0, |kick| kick as u64 + 1),
+        );
+        bytes.push(engine.back_to_back as u8);
+        write_varint(
+            &mut bytes,
+            engine.combo.map_or(0, |combo| u64::from(combo) + 1),
+        );
+        write_varint(&mut bytes, engine.garbage_drawn);
+        write_f64(&mut bytes, engine.garbage_timer);
+        write_f64(&mut bytes, engine.elapsed_ms);
+
+        write_varint(&mut bytes, self.frame);
+        write_varint(&mut bytes, u64::from(self.finesse_faults));
+        let replay = self.replay.to_bytes();
+        write_varint(&mut bytes, replay.len() as u64);
+        bytes.extend_from_slice(&replay);
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<SavedGame, String> {
+        let mut reader = Reader::new(bytes);
+        if reader.take(MAGIC.len())? != MAGIC {
+            return Err("Not a saved game".to_string());
+        }
+        let version = reader.u8()?;
+        if version != SAVE_FORMAT_VERSION {
+            return Err(format!("Unsupported save version {}", version));
+        }
+        let mode_name = reader.string()?;
+        let mode = GameMode::from_name(&mode_name)
+            .ok_or_else(|| format!("Unknown mode `{}`", mode_name))?;
+
+        let seed = u32::from_le_bytes(reader.array()?);
+        let randomizer = randomizer_from_code(reader.u8()?)?;
+        let rotation_system = rotation_system_from_code(reader.u8()?)?;
+        let pieces_drawn = reader.varint()?;
+
+        let rules = Rules {
+            lock_delay_ms: reader.f64()?,
+            max_lock_resets: reader.varint_u32()?,
+            start_level: reader.varint_u32()?,
+            preview_count: reader.varint()? as usize,
+            line_goal: reader.varint_u32()?.checked_sub(1),
+            max_level: reader.varint_u32()?.checked_sub(1),
+            garbage_rows: reader.varint_u32()?,
+            garbage_interval_ms: Some(reader.f64()?).filter(|&interval| interval > 0.0),
+            time_limit_ms: Some(reader.f64()?).filter(|&limit| limit > 0.0),
+        };
+        rules.validate()?;
+        let stats = Stats {
+            score: reader.varint_u32()?,
+            lines: reader.varint_u32()?,
+            level: reader.varint_u32()?,
+            pieces: reader.varint_u32()?,
+        };
+
+        let color_count = reader.varint()?;
+        let mut colors = Vec::new();
+        for _ in 0..color_count {
+            colors.push(reader.string()?);
+        }
+        let height = reader.varint()?;
+        let width = reader.varint()?;
+        let mut cells = Vec::new();
+        for _ in 0..height {
+            let mut row = Vec::new();
+            for _ in 0..width {
+                row.push(match reader.varint()? {
+                    0 => None,
+                    code => Some(
+                        colors
+                            .get(code as usize - 1)
+                            .cloned()
+                            .ok_or_else(|| format!("Unknown colour {}", code))?,
+                    ),
+                });
+            }
+            cells.push(row);
+        }
+
+        let piece_type = reader.piece_type()?;
+        let piece_row = reader.i8()?;
+        let piece_col = reader.i8()?;
+        let piece_rotation = reader.u8()?;
+
+        let held_piece = if reader.bool()? {
+            Some(reader.piece_type()?)
+        } else {
+            None
+        };
+        let can_hold = reader.bool()?;
+        let queue_len = reader.varint()?;
+        let mut next_queue = Vec::new();
+        for _ in 0..queue_len {
+            next_queue.push(reader.piece_type()?);
+        }
+
+        let time_since_last_drop = reader.f64()?;
+        let lock_timer = reader.f64()?;
+        let lock_resets = reader.varint_u32()?;
+        let lowest_row = reader.i8()?;
+        let last_kick = reader.varint()?.checked_sub(1).map(|kick| kick as usize);
+        let back_to_back = reader.bool()?;
+        let combo = reader.varint_u32()?.checked_sub(1);
+        let garbage_drawn = reader.varint()?;
+        let garbage_timer = reader.f64()?;
+        let elapsed_ms = reader.f64()?;
+
+        let frame = reader.varint()?;
+        let finesse_faults = reader.varint_u32()?;
+        let replay_len = reader.varint()? as usize;
+        let replay = Replay::from_bytes(reader.take(replay_len)?)?;
+
+        if !reader.is_empty() {
+            return Err("Unexpected data after the saved game".to_string());
+        }
+
+        Ok(SavedGame {
+            mode,
+            engine: EngineSnapshot {
+                seed,
+                randomizer,
+                rotation_system,
+                pieces_drawn,
+                rules,
+                stats,
+                cells,
+                piece_type,
+                piece_row,
+                piece_col,
+                piece_rotation,
+                held_piece,
+                can_hold,
+                next_queue,
+                time_since_last_drop,
+                lock_timer,
+                lock_resets,
+                lowest_row,
+                last_kick,
+                back_to_back,
+                combo,
+                garbage_drawn,
+                garbage_timer,
+                elapsed_ms,
+            },
+            replay,
+            frame,
+            finesse_faults,
+        })
+    }
+
+    /// The binary format as URL-safe base64, for `localStorage`.
+    pub fn to_base64(&self) -> String {
+        base64_encode(&self.to_bytes())
+    }
+
+    pub fn from_base64(text: &str) -> Result<SavedGame, String> {
+        SavedGame::from_bytes(&base64_decode(text.trim())?)
+    }
+}
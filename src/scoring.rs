@@ -0,0 +1,220 @@
+use crate::{
+    board::Board,
+    pieces::{PieceState, PieceType},
+};
+
+pub const SOFT_DROP_POINTS_PER_CELL: u32 = 1;
+pub const HARD_DROP_POINTS_PER_CELL: u32 = 2;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Spin {
+    None,
+    Mini,
+    Full,
+}
+
+/// What a single lock achieved. Emitted for every lock that cleared lines or
+/// counted as a T-spin.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ClearEvent {
+    pub lines: u32,
+    pub spin: Spin,
+    pub back_to_back: bool,
+    /// How many clears in a row preceded this one; `0` for the first.
+    pub combo: u32,
+    pub perfect_clear: bool,
+    pub points: u32,
+}
+
+impl ClearEvent {
+    /// A label such as "B2B T-SPIN DOUBLE" for the UI.
+    pub fn label(&self) -> String {
+        let lines = match self.lines {
+            0 => "",
+            1 => "SINGLE",
+            2 => "DOUBLE",
+            3 => "TRIPLE",
+            _ => "TETRIS",
+        };
+
+        let mut parts = Vec::new();
+        if self.back_to_back {
+            parts.push("B2B".to_string());
+        }
+        match self.spin {
+            Spin::None => {}
+            Spin::Mini => parts.push("T-SPIN MINI".to_string()),
+            Spin::Full => parts.push("T-SPIN".to_string()),
+        }
+        if !lines.is_empty() {
+            parts.push(lines.to_string());
+        }
+        if self.combo > 0 {
+            parts.push(format!("{} COMBO", self.combo));
+        }
+        if self.perfect_clear {
+            parts.push("PERFECT CLEAR".to_string());
+        }
+        parts.join(" ")
+    }
+}
+
+/// Guideline scoring state carried between locks.
+#[derive(Clone, Debug)]
+pub struct Scoring {
+    back_to_back: bool,
+    combo: Option<u32>,
+}
+
+impl Default for Scoring {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Scoring {
+    pub fn new() -> Scoring {
+        Scoring {
+            back_to_back: false,
+            combo: None,
+        }
+    }
+
+    /// Carries on chains from a saved game.
+    pub fn resume(back_to_back: bool, combo: Option<u32>) -> Scoring {
+        Scoring {
+            back_to_back,
+            combo,
+        }
+    }
+
+    /// Whether the last clear was a tetris or T-spin, so the next one can
+    /// be back-to-back.
+    pub fn back_to_back(&self) -> bool {
+        self.back_to_back
+    }
+
+    /// Clears in a row so far, or `None` if the last lock cleared nothing.
+    pub fn combo(&self) -> Option<u32> {
+        self.combo
+    }
+
+    /// Scores a lock that cleared `lines` on `level`, updating the
+    /// back-to-back and combo chains.
+    pub fn score_lock(
+        &mut self,
+        lines: u32,
+        spin: Spin,
+        perfect_clear: bool,
+        level: u32,
+    ) -> Option<ClearEvent> {
+        if lines == 0 {
+            self.combo = None;
+            if spin == Spin::None {
+                return None;
+            }
+        }
+
+        let base = match (spin, lines) {
+            (Spin::None, 0) => 0,
+            (Spin::None, 1) => 100,
+            (Spin::None, 2) => 300,
+            (Spin::None, 3) => 500,
+            (Spin::None, _) => 800,
+            (Spin::Mini, 0) => 100,
+            (Spin::Mini, 1) => 200,
+            (Spin::Mini, _) => 400,
+            (Spin::Full, 0) => 400,
+            (Spin::Full, 1) => 800,
+            (Spin::Full, 2) => 1200,
+            (Spin::Full, _) => 1600,
+        };
+
+        let mut back_to_back = false;
+        let mut combo = 0;
+        if lines > 0 {
+            let difficult = lines >= 4 || spin != Spin::None;
+            back_to_back = difficult && self.back_to_back;
+            self.back_to_back = difficult;
+
+            combo = self.combo.map_or(0, |combo| combo + 1);
+            self.combo = Some(combo);
+        }
+
+        let mut points = if back_to_back { base * 3 / 2 } else { base };
+        points += 50 * combo;
+        if perfect_clear {
+            points += match lines {
+                1 => 800,
+                2 => 1200,
+                3 => 1800,
+                _ if back_to_back => 3200,
+                _ => 2000,
+            };
+        }
+
+        Some(ClearEvent {
+            lines,
+            spin,
+            back_to_back,
+            combo,
+            perfect_clear,
+            points: points * level,
+        })
+    }
+}
+
+/// The 3-corner rule. `last_kick` is the index of the kick used by the last
+/// successful move if that move was a rotation.
+pub fn detect_t_spin(board: &Board, piece: &PieceState, last_kick: Option<usize>) -> Spin {
+    if piece.piece_type != PieceType::T {
+        return Spin::None;
+    }
+    let last_kick = match last_kick {
+        Some(last_kick) => last_kick,
+        None => return Spin::None,
+    };
+
+    let blocks: Vec<(i8, i8)> = piece.iter_blocks().collect();
+    // The centre of a T is the only block with three neighbours.
+    let (center_r, center_c) = match blocks.iter().find(|&&(r, c)| {
+        [(-1, 0), (1, 0), (0, -1), (0, 1)]
+            .iter()
+            .filter(|&&(dr, dc)| blocks.contains(&(r + dr, c + dc)))
+            .count()
+            == 3
+    }) {
+        Some(&center) => center,
+        None => return Spin::None,
+    };
+
+    // The nub is the neighbour whose opposite cell is empty.
+    let (nub_r, nub_c) = [(-1, 0), (1, 0), (0, -1), (0, 1)]
+        .iter()
+        .copied()
+        .find(|&(dr, dc)| !blocks.contains(&(center_r - dr, center_c - dc)))
+        .unwrap_or((-1, 0));
+
+    let is_filled = |dr: i8, dc: i8| !board.is_open_cell(center_r + dr, center_c + dc);
+    let corners = [(-1, -1), (-1, 1), (1, -1), (1, 1)];
+    let filled_corners = corners
+        .iter()
+        .filter(|&&(dr, dc)| is_filled(dr, dc))
+        .count();
+    if filled_corners < 3 {
+        return Spin::None;
+    }
+
+    let front_corners = corners
+        .iter()
+        .filter(|&&(dr, dc)| (nub_r != 0 && dr == nub_r) || (nub_c != 0 && dc == nub_c))
+        .filter(|&&(dr, dc)| is_filled(dr, dc))
+        .count();
+
+    // SRS's fifth kick always produces a full T-spin.
+    if front_corners == 2 || last_kick == 4 {
+        Spin::Full
+    } else {
+        Spin::Mini
+    }
+}
@@ -0,0 +1,48 @@
+/// The length of one logic step: the simulation always runs at 60 Hz.
+pub const STEP_MS: f64 = 1000.0 / 60.0;
+
+/// Splits variable frame times into whole logic steps, carrying the
+/// remainder over to the next frame.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FixedTimestep {
+    accumulator: f64,
+    frame: u64,
+}
+
+impl FixedTimestep {
+    pub fn new() -> FixedTimestep {
+        FixedTimestep::default()
+    }
+
+    /// Carries on counting from `frame`, e.g. in a restored game.
+    pub fn resume_at(frame: u64) -> FixedTimestep {
+        FixedTimestep {
+            accumulator: 0.0,
+            frame,
+        }
+    }
+
+    /// Adds `delta_time` and returns how many steps are now due. Each one
+    /// is counted as a frame.
+    pub fn advance(&mut self, delta_time: f64) -> u32 {
+        self.accumulator += delta_time.max(0.0);
+        let mut steps = 0;
+        while self.accumulator >= STEP_MS {
+            self.accumulator -= STEP_MS;
+            steps += 1;
+        }
+        self.frame += u64::from(steps);
+        steps
+    }
+
+    /// How far between the last step and the next one the current time
+    /// is, from `0.0` to `1.0`, for interpolating what is drawn.
+    pub fn alpha(&self) -> f64 {
+        self.accumulator / STEP_MS
+    }
+
+    /// The number of steps taken so far.
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
+}
@@ -0,0 +1,170 @@
+/// What a finger on the playfield asked for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Gesture {
+    Tap,
+    ShiftLeft,
+    ShiftRight,
+    SoftDrop,
+    HardDrop,
+}
+
+/// Gesture thresholds. Distances are in CSS pixels, times in milliseconds.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GestureConfig {
+    /// A touch that moves less than this and ends in time is a tap.
+    pub tap_max_distance: f64,
+    pub tap_max_ms: f64,
+    /// Horizontal travel per shift while dragging.
+    pub shift_distance: f64,
+    /// Downward travel per soft drop while dragging.
+    pub soft_drop_distance: f64,
+    /// A downward swipe at least this long and this fast, in pixels per
+    /// millisecond, hard drops when released.
+    pub flick_min_distance: f64,
+    pub flick_min_velocity: f64,
+}
+
+impl Default for GestureConfig {
+    fn default() -> Self {
+        GestureConfig::for_cell_size(20.0)
+    }
+}
+
+impl GestureConfig {
+    /// Thresholds that follow the finger one cell at a time.
+    pub fn for_cell_size(cell_size: f64) -> GestureConfig {
+        GestureConfig {
+            tap_max_distance: cell_size / 2.0,
+            tap_max_ms: 250.0,
+            shift_distance: cell_size,
+            soft_drop_distance: cell_size,
+            flick_min_distance: cell_size * 3.0,
+            flick_min_velocity: 1.0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Axis {
+    Horizontal,
+    Vertical,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Touch {
+    start_x: f64,
+    start_y: f64,
+    start_time: f64,
+    anchor_x: f64,
+    anchor_y: f64,
+    axis: Option<Axis>,
+}
+
+/// Turns the position of a single tracked touch into gestures. A drag locks
+/// to whichever axis it first moves a full step along.
+#[derive(Clone, Debug, Default)]
+pub struct GestureRecognizer {
+    config: GestureConfig,
+    touch: Option<Touch>,
+}
+
+impl GestureRecognizer {
+    pub fn new(config: GestureConfig) -> GestureRecognizer {
+        GestureRecognizer {
+            config,
+            touch: None,
+        }
+    }
+
+    pub fn config(&self) -> GestureConfig {
+        self.config
+    }
+
+    pub fn set_config(&mut self, config: GestureConfig) {
+        self.config = config;
+    }
+
+    pub fn start(&mut self, x: f64, y: f64, time: f64) {
+        self.touch = Some(Touch {
+            start_x: x,
+            start_y: y,
+            start_time: time,
+            anchor_x: x,
+            anchor_y: y,
+            axis: None,
+        });
+    }
+
+    pub fn move_to(&mut self, x: f64, y: f64) -> Vec<Gesture> {
+        let config = self.config;
+        let touch = match self.touch.as_mut() {
+            Some(touch) => touch,
+            None => return Vec::new(),
+        };
+
+        if touch.axis.is_none() {
+            let dx = x - touch.anchor_x;
+            let dy = y - touch.anchor_y;
+            if dx.abs() >= config.shift_distance && dx.abs() >= dy.abs() {
+                touch.axis = Some(Axis::Horizontal);
+            } else if dy >= config.soft_drop_distance {
+                touch.axis = Some(Axis::Vertical);
+            }
+        }
+
+        let mut gestures = Vec::new();
+        match touch.axis {
+            Some(Axis::Horizontal) => {
+                while x - touch.anchor_x >= config.shift_distance {
+                    touch.anchor_x += config.shift_distance;
+                    gestures.push(Gesture::ShiftRight);
+                }
+                while touch.anchor_x - x >= config.shift_distance {
+                    touch.anchor_x -= config.shift_distance;
+                    gestures.push(Gesture::ShiftLeft);
+                }
+            }
+            Some(Axis::Vertical) => {
+                // Moving back up rearms the drag instead of banking distance.
+                touch.anchor_y = touch.anchor_y.min(y);
+                while y - touch.anchor_y >= config.soft_drop_distance {
+                    touch.anchor_y += config.soft_drop_distance;
+                    gestures.push(Gesture::SoftDrop);
+                }
+            }
+            None => {}
+        }
+        gestures
+    }
+
+    /// Finishes the touch, adding a tap or a flick to any final movement.
+    pub fn end(&mut self, x: f64, y: f64, time: f64) -> Vec<Gesture> {
+        let mut gestures = self.move_to(x, y);
+        let touch = match self.touch.take() {
+            Some(touch) => touch,
+            None => return gestures,
+        };
+
+        let dx = x - touch.start_x;
+        let dy = y - touch.start_y;
+        let duration = (time - touch.start_time).max(1.0);
+
+        if touch.axis != Some(Axis::Horizontal)
+            && dy >= self.config.flick_min_distance
+            && dy / duration >= self.config.flick_min_velocity
+        {
+            gestures.push(Gesture::HardDrop);
+        } else if touch.axis.is_none()
+            && dx.hypot(dy) <= self.config.tap_max_distance
+            && duration <= self.config.tap_max_ms
+        {
+            gestures.push(Gesture::Tap);
+        }
+        gestures
+    }
+
+    /// Drops the tracked touch without producing anything.
+    pub fn cancel(&mut self) {
+        self.touch = None;
+    }
+}
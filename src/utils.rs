@@ -1,4 +1,4 @@
-use web_sys::js_sys::Promise;
+use web_sys::{js_sys::Promise, Storage};
 
 pub fn set_panic_hook() {
     // When the `console_error_panic_hook` feature is enabled, we can call the
@@ -19,3 +19,9 @@ pub fn sleep(ms: i32) -> Promise {
             .unwrap();
     })
 }
+
+/// The page's `localStorage`, if the browser allows it.
+pub fn local_storage() -> Option<Storage> {
+    let window = web_sys::window().expect("no global `window` exists");
+    window.local_storage().ok().flatten()
+}
@@ -0,0 +1,446 @@
+//! Native tests for the DOM-free game engine.
+
+use tetris::{
+    engine::{self, Engine, Rules, TopOut},
+    mode::GameMode,
+    pieces::PieceType,
+    random::RandomizerKind,
+    rotation::RotationSystemKind,
+    scoring::Spin,
+    timestep::STEP_MS,
+};
+
+fn engine() -> Engine {
+    engine_with(RotationSystemKind::Srs)
+}
+
+fn engine_with(rotation_system: RotationSystemKind) -> Engine {
+    Engine::new(0, RandomizerKind::SevenBag.build(), rotation_system.build())
+}
+
+#[test]
+fn ghost_piece_rests_on_the_floor() {
+    let engine = engine();
+    let ghost = engine.ghost_piece();
+    let lowest_row = ghost.iter_blocks().map(|(r, _)| r).max().unwrap();
+    assert_eq!(lowest_row, 19);
+}
+
+#[test]
+fn hard_drop_locks_the_piece_and_spawns_the_next_one() {
+    let mut engine = engine();
+    let next = engine.next_piece().clone();
+    let landed: Vec<_> = engine.ghost_piece().iter_blocks().collect();
+
+    engine.hard_drop();
+
+    for (r, c) in landed {
+        assert!(engine.board().cell(r as usize, c as usize).is_some());
+    }
+    assert_eq!(engine.current_piece().piece_type(), &next);
+}
+
+#[test]
+fn gravity_moves_the_piece_once_per_interval() {
+    let mut engine = engine();
+    let start_row = engine.current_piece().row();
+
+    engine.tick(999.0);
+    assert_eq!(engine.current_piece().row(), start_row);
+
+    engine.tick(1.0);
+    assert_eq!(engine.current_piece().row(), start_row + 1);
+}
+
+#[test]
+fn hold_is_only_allowed_once_per_piece() {
+    let mut engine = engine();
+    let first = engine.current_piece().piece_type().clone();
+
+    engine.hold();
+    assert_eq!(engine.held_piece(), Some(&first));
+
+    let second = engine.current_piece().piece_type().clone();
+    engine.hold();
+    assert_eq!(engine.current_piece().piece_type(), &second);
+}
+
+#[test]
+fn stacking_to_the_ceiling_ends_the_game() {
+    let mut engine = engine();
+
+    for _ in 0..100 {
+        if engine.is_game_over() {
+            break;
+        }
+        engine.hard_drop();
+    }
+
+    assert!(engine.is_game_over());
+    assert!(engine.top_out().is_some());
+
+    let stats = engine.stats();
+    engine.hard_drop();
+    engine.tick(10_000.0);
+    assert_eq!(engine.stats(), stats);
+}
+
+#[test]
+fn locking_partly_above_the_field_ends_the_game() {
+    let mut snapshot = engine().snapshot();
+    snapshot.piece_type = PieceType::Z;
+    snapshot.piece_row = -3;
+    snapshot.piece_rotation = 0;
+    // Leave the first column open so nothing clears.
+    for row in snapshot.cells.iter_mut().skip(1) {
+        for cell in row.iter_mut().skip(1) {
+            *cell = Some("#808080".to_string());
+        }
+    }
+    let mut engine = Engine::restore(&snapshot).unwrap();
+    let landing: Vec<_> = engine.ghost_piece().iter_blocks().collect();
+    assert!(landing.iter().any(|&(r, _)| r < 0));
+    assert!(landing.iter().any(|&(r, _)| r >= 0));
+
+    engine.hard_drop();
+
+    assert!(engine.is_game_over());
+    assert_eq!(engine.top_out(), Some(TopOut::LockOut));
+}
+
+#[test]
+fn every_piece_has_four_rotation_states() {
+    let mut engine = engine();
+
+    for _ in 0..7 {
+        let start: Vec<_> = engine.current_piece().iter_blocks().collect();
+        for _ in 0..4 {
+            assert!(engine.rotate_clockwise());
+        }
+        let after: Vec<_> = engine.current_piece().iter_blocks().collect();
+        assert_eq!(start, after);
+
+        engine.rotate_clockwise();
+        engine.rotate_counter_clockwise();
+        let back: Vec<_> = engine.current_piece().iter_blocks().collect();
+        assert_eq!(start, back);
+
+        engine.hard_drop();
+    }
+}
+
+#[test]
+fn rotating_against_the_wall_kicks_the_piece_back_in() {
+    let mut engine = engine();
+
+    while engine.current_piece().piece_type() != &PieceType::Straight {
+        engine.hard_drop();
+    }
+
+    engine.rotate_clockwise();
+    while engine.move_left() {}
+    assert!(engine.rotate_clockwise());
+    assert!(engine.current_piece().iter_blocks().all(|(_, c)| c >= 0));
+}
+
+#[test]
+fn every_rotation_system_cycles_back_to_the_spawn_state() {
+    for kind in [
+        RotationSystemKind::Srs,
+        RotationSystemKind::Ars,
+        RotationSystemKind::Nes,
+    ] {
+        let mut engine = engine_with(kind);
+        engine.tick(1000.0);
+
+        for _ in 0..7 {
+            let start: Vec<_> = engine.current_piece().iter_blocks().collect();
+            for _ in 0..4 {
+                engine.rotate_clockwise();
+            }
+            let after: Vec<_> = engine.current_piece().iter_blocks().collect();
+            assert_eq!(start, after, "{:?}", kind);
+            engine.hard_drop();
+        }
+    }
+}
+
+/// An ARS engine with a T whose 3x3 box is at row 10, column 4, in
+/// `rotation`, on an empty board apart from `filled`.
+fn ars_t_setup(rotation: u8, filled: &[(usize, usize)]) -> Engine {
+    let mut snapshot = engine_with(RotationSystemKind::Ars).snapshot();
+    snapshot.piece_type = PieceType::T;
+    snapshot.piece_row = 10;
+    snapshot.piece_col = 4;
+    snapshot.piece_rotation = rotation;
+    for &(row, col) in filled {
+        snapshot.cells[row][col] = Some("#808080".to_string());
+    }
+    Engine::restore(&snapshot).unwrap()
+}
+
+#[test]
+fn ars_wont_kick_when_the_centre_column_is_blocked_first() {
+    // The clockwise state needs the top centre cell. A kick right would fit.
+    let mut engine = ars_t_setup(0, &[(10, 5)]);
+
+    assert!(!engine.rotate_clockwise());
+    assert_eq!(engine.snapshot().piece_rotation, 0);
+    assert_eq!(engine.snapshot().piece_col, 4);
+}
+
+#[test]
+fn ars_kicks_right_before_left() {
+    // The flat-down state needs the bottom left cell; both kicks would fit.
+    let mut engine = ars_t_setup(1, &[(12, 4)]);
+
+    assert!(engine.rotate_clockwise());
+    assert_eq!(engine.snapshot().piece_rotation, 2);
+    assert_eq!(engine.snapshot().piece_col, 5);
+}
+
+#[test]
+fn grounded_pieces_wait_for_the_lock_delay() {
+    let mut engine = engine();
+    while engine.soft_drop() {}
+    assert!(engine.is_grounded());
+
+    engine.tick(499.0);
+    assert_eq!(engine.stats().pieces, 0);
+
+    engine.tick(1.0);
+    assert_eq!(engine.stats().pieces, 1);
+}
+
+#[test]
+fn moving_on_the_ground_resets_the_lock_delay_a_limited_number_of_times() {
+    let mut engine = engine();
+    while engine.soft_drop() {}
+
+    let mut resets = 0;
+    while engine.stats().pieces == 0 {
+        engine.tick(400.0);
+        if resets % 2 == 0 {
+            engine.move_left();
+        } else {
+            engine.move_right();
+        }
+        resets += 1;
+    }
+
+    assert!(resets > 1);
+    assert!(resets <= engine.rules().max_lock_resets + 1);
+}
+
+#[test]
+fn gravity_speeds_up_with_level_and_reaches_20g() {
+    assert_eq!(engine::drop_interval_ms(1), 1000.0);
+    for level in 1..engine::MAX_GRAVITY_LEVEL {
+        assert!(engine::drop_interval_ms(level + 1) < engine::drop_interval_ms(level));
+    }
+    assert_eq!(engine::drop_interval_ms(engine::MAX_GRAVITY_LEVEL), 0.0);
+}
+
+#[test]
+fn the_level_cap_holds_the_speed() {
+    let mut engine = engine();
+    engine.set_rules(Rules {
+        start_level: 18,
+        max_level: Some(15),
+        ..Rules::default()
+    });
+
+    assert_eq!(engine.level(), 15);
+    assert!(engine::drop_interval_ms(engine.level()) > 0.0);
+}
+
+#[test]
+fn pieces_fall_to_the_stack_at_once_under_20g() {
+    let mut engine = engine();
+    engine.set_rules(Rules {
+        start_level: engine::MAX_GRAVITY_LEVEL,
+        ..Rules::default()
+    });
+    assert_eq!(engine.level(), engine::MAX_GRAVITY_LEVEL);
+
+    engine.tick(0.0);
+    assert!(engine.is_grounded());
+}
+
+#[test]
+fn the_same_seed_deals_the_same_pieces() {
+    let deal = |seed| {
+        let mut engine = Engine::new(
+            seed,
+            RandomizerKind::SevenBag.build(),
+            RotationSystemKind::Srs.build(),
+        );
+        (0..21)
+            .map(|_| {
+                let piece_type = engine.current_piece().piece_type().clone();
+                engine.hold();
+                engine.hard_drop();
+                piece_type
+            })
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(deal(42), deal(42));
+    assert_ne!(deal(42), deal(43));
+}
+
+#[test]
+fn changing_the_preview_length_keeps_the_sequence() {
+    let deal = |preview_counts: &[usize]| {
+        let mut engine = engine();
+        preview_counts
+            .iter()
+            .map(|&preview_count| {
+                engine.set_rules(Rules {
+                    preview_count,
+                    ..engine.rules()
+                });
+                assert_eq!(engine.next_pieces().count(), preview_count.clamp(1, 6));
+                let piece_type = engine.current_piece().piece_type().clone();
+                engine.hard_drop();
+                piece_type
+            })
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(deal(&[1, 1, 1, 1, 1, 1]), deal(&[6, 1, 3, 0, 9, 2]));
+}
+
+#[test]
+fn reaching_the_line_goal_ends_the_game() {
+    let engine = engine();
+    let mut snapshot = engine.snapshot();
+    snapshot.rules.line_goal = Some(1);
+    // Fill the bottom row around where the current piece will land.
+    let landing: Vec<_> = engine.ghost_piece().iter_blocks().collect();
+    let bottom = snapshot.cells.len() - 1;
+    for (col, cell) in snapshot.cells[bottom].iter_mut().enumerate() {
+        if !landing.contains(&(bottom as i8, col as i8)) {
+            *cell = Some("#808080".to_string());
+        }
+    }
+    let mut engine = Engine::restore(&snapshot).unwrap();
+    assert!(!engine.is_game_over());
+
+    engine.hard_drop();
+
+    assert_eq!(engine.stats().lines, 1);
+    assert!(engine.goal_reached());
+    assert!(engine.is_game_over());
+    assert!(engine.top_out().is_none());
+}
+
+/// An SRS engine holding a T with its 3x3 box at `(row, col)` in `rotation`,
+/// over a stack where `filled` returns which cells hold blocks.
+fn t_spin_setup(row: i8, col: i8, rotation: u8, filled: impl Fn(usize, usize) -> bool) -> Engine {
+    let mut snapshot = engine().snapshot();
+    snapshot.piece_type = PieceType::T;
+    snapshot.piece_row = row;
+    snapshot.piece_col = col;
+    snapshot.piece_rotation = rotation;
+    for (r, cells) in snapshot.cells.iter_mut().enumerate() {
+        for (c, cell) in cells.iter_mut().enumerate() {
+            *cell = Some("#808080".to_string()).filter(|_| filled(r, c));
+        }
+    }
+    Engine::restore(&snapshot).unwrap()
+}
+
+#[test]
+fn rotating_a_t_into_a_covered_slot_is_a_t_spin_double() {
+    // A slot at columns 3-5 of row 18 with its stem at (19, 4), covered
+    // by an overhang at (17, 3).
+    let mut engine = t_spin_setup(17, 3, 1, |r, c| match r {
+        17 => c == 3,
+        18 => !(3..=5).contains(&c),
+        19 => c != 4,
+        _ => false,
+    });
+
+    assert!(engine.rotate_clockwise());
+    engine.hard_drop();
+
+    let events = engine.take_clear_events();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].spin, Spin::Full);
+    assert_eq!(events[0].lines, 2);
+}
+
+#[test]
+fn a_t_spin_without_both_front_corners_is_a_mini() {
+    // The T turns nub up with its back corners on row 19 and only the
+    // left front corner at (17, 3) filled.
+    let mut engine = t_spin_setup(17, 3, 1, |r, c| match r {
+        17 => c == 3,
+        18 => !(3..=5).contains(&c),
+        19 => c != 4,
+        _ => false,
+    });
+
+    assert!(engine.rotate_counter_clockwise());
+    engine.hard_drop();
+
+    let events = engine.take_clear_events();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].spin, Spin::Mini);
+    assert_eq!(events[0].lines, 1);
+}
+
+#[test]
+fn a_t_slid_into_place_is_not_a_t_spin() {
+    // The same three corners as a T-spin, but the T shifts in nub up
+    // instead of rotating.
+    let mut engine = t_spin_setup(17, 4, 0, |r, c| match r {
+        17 => c == 3,
+        18 => !(3..=6).contains(&c),
+        19 => c != 9,
+        _ => false,
+    });
+
+    assert!(engine.move_left());
+    engine.hard_drop();
+
+    assert_eq!(engine.stats().pieces, 1);
+    assert!(engine.take_clear_events().is_empty());
+}
+
+#[test]
+fn ultra_ends_when_its_time_runs_out() {
+    let mut engine = Engine::with_rules(
+        0,
+        RandomizerKind::SevenBag.build(),
+        RotationSystemKind::Srs.build(),
+        // The first piece never locks, so the stack can't top out first.
+        Rules {
+            lock_delay_ms: f64::INFINITY,
+            ..GameMode::Ultra2.rules(Rules::default())
+        },
+    );
+    for _ in 0..7199 {
+        engine.tick(STEP_MS);
+    }
+    assert!(!engine.is_game_over());
+
+    // A grounded piece due to lock on the last tick stays where it is.
+    while engine.soft_drop() {}
+    engine.set_rules(Rules {
+        lock_delay_ms: 0.0,
+        ..engine.rules()
+    });
+    engine.tick(STEP_MS);
+
+    assert_eq!(engine.stats().pieces, 0);
+    assert!(engine.is_time_up());
+    assert!(engine.is_game_over());
+    assert!(engine.top_out().is_none());
+    assert!((engine.elapsed_ms() - 120_000.0).abs() < 1e-6);
+
+    let elapsed_ms = engine.elapsed_ms();
+    engine.tick(60_000.0);
+    assert_eq!(engine.elapsed_ms(), elapsed_ms);
+}
@@ -0,0 +1,99 @@
+//! Native tests for counting finesse faults.
+
+use tetris::{
+    engine::Engine,
+    finesse::FinesseTracker,
+    input::{Handling, InputHandler},
+    keybinds::Action,
+    random::RandomizerKind,
+    replay::{apply_input, ReplayInput},
+    rotation::RotationSystemKind,
+    timestep::STEP_MS,
+};
+
+struct Player {
+    engine: Engine,
+    input: InputHandler,
+    finesse: FinesseTracker,
+}
+
+impl Player {
+    fn new() -> Player {
+        let engine = Engine::new(
+            3,
+            RandomizerKind::SevenBag.build(),
+            RotationSystemKind::Srs.build(),
+        );
+        Player {
+            finesse: FinesseTracker::new(&engine),
+            input: InputHandler::new(Handling::default()),
+            engine,
+        }
+    }
+
+    fn send(&mut self, input: ReplayInput) {
+        self.finesse.before_input(input);
+        apply_input(&mut self.engine, &mut self.input, input);
+        self.finesse.after_update(&self.engine);
+    }
+
+    fn tap(&mut self, action: Action) {
+        self.send(ReplayInput::Press(action));
+        self.send(ReplayInput::Release(action));
+    }
+
+    fn wait(&mut self, frames: u32) {
+        for _ in 0..frames {
+            self.input.update(&mut self.engine, STEP_MS);
+            self.engine.tick(STEP_MS);
+            self.finesse.after_update(&self.engine);
+        }
+    }
+}
+
+#[test]
+fn dropping_in_place_and_das_to_the_wall_are_clean() {
+    let mut player = Player::new();
+    player.tap(Action::HardDrop);
+
+    player.send(ReplayInput::Press(Action::MoveLeft));
+    player.wait(30);
+    player.send(ReplayInput::Release(Action::MoveLeft));
+    player.tap(Action::HardDrop);
+
+    assert_eq!(player.engine.stats().pieces, 2);
+    assert_eq!(player.finesse.faults(), 0);
+}
+
+#[test]
+fn wasted_moves_and_rotations_are_faults() {
+    let mut player = Player::new();
+    player.tap(Action::MoveLeft);
+    player.tap(Action::MoveRight);
+    player.tap(Action::MoveLeft);
+    player.tap(Action::HardDrop);
+    assert_eq!(player.finesse.faults(), 2);
+
+    for _ in 0..4 {
+        player.tap(Action::RotateClockwise);
+    }
+    player.tap(Action::HardDrop);
+    assert_eq!(player.finesse.faults(), 6);
+}
+
+#[test]
+fn soft_dropped_and_held_pieces_are_not_judged() {
+    let mut player = Player::new();
+    player.tap(Action::RotateClockwise);
+    player.tap(Action::RotateCounterClockwise);
+    player.tap(Action::Hold);
+    player.tap(Action::HardDrop);
+
+    player.tap(Action::MoveLeft);
+    player.tap(Action::MoveRight);
+    player.tap(Action::SoftDrop);
+    player.tap(Action::HardDrop);
+
+    assert_eq!(player.engine.stats().pieces, 2);
+    assert_eq!(player.finesse.faults(), 0);
+}
@@ -0,0 +1,31 @@
+//! Native tests for gamepad button mapping.
+
+use tetris::{gamepad::GamepadInput, keybinds::Action};
+
+fn buttons(pressed: &[usize]) -> Vec<bool> {
+    (0..17).map(|button| pressed.contains(&button)).collect()
+}
+
+#[test]
+fn only_changed_buttons_produce_events() {
+    let mut gamepad = GamepadInput::default();
+
+    assert_eq!(
+        gamepad.update(&buttons(&[14])),
+        vec![(Action::MoveLeft, true)]
+    );
+    assert!(gamepad.update(&buttons(&[14])).is_empty());
+    assert_eq!(
+        gamepad.update(&buttons(&[13])),
+        vec![(Action::SoftDrop, true), (Action::MoveLeft, false)]
+    );
+}
+
+#[test]
+fn disconnecting_releases_held_buttons() {
+    let mut gamepad = GamepadInput::default();
+    gamepad.update(&buttons(&[15]));
+
+    assert_eq!(gamepad.release_all(), vec![(Action::MoveRight, false)]);
+    assert!(gamepad.release_all().is_empty());
+}
@@ -0,0 +1,111 @@
+//! Native tests for garbage rows and the Dig modes.
+
+use tetris::{
+    board::Board,
+    engine::{Engine, Rules, TopOut},
+    garbage::{HoleGenerator, GARBAGE_COLOR},
+    random::RandomizerKind,
+    rotation::RotationSystemKind,
+};
+
+fn dig_engine(garbage_rows: u32, garbage_interval_ms: Option<f64>) -> Engine {
+    Engine::with_rules(
+        11,
+        RandomizerKind::SevenBag.build(),
+        RotationSystemKind::Srs.build(),
+        Rules {
+            garbage_rows,
+            garbage_interval_ms,
+            ..Rules::default()
+        },
+    )
+}
+
+#[test]
+fn holes_are_seeded_and_never_line_up() {
+    let mut holes = HoleGenerator::new(5);
+    let mut again = HoleGenerator::new(5);
+    let mut last = None;
+    for _ in 0..200 {
+        let hole = holes.next_hole(10);
+        assert_eq!(again.next_hole(10), hole);
+        assert!(hole < 10);
+        assert_ne!(Some(hole), last);
+        last = Some(hole);
+    }
+}
+
+#[test]
+fn garbage_rises_from_the_bottom() {
+    let mut board = Board::new();
+    assert!(board.insert_garbage_rows(&[2, 7]));
+
+    assert_eq!(board.garbage_rows(), 2);
+    assert_eq!(board.cell(18, 2), None);
+    assert_eq!(board.cell(19, 7), None);
+    assert_eq!(board.cell(19, 2), Some(GARBAGE_COLOR));
+
+    // A full field loses its top row.
+    assert!(!board.insert_garbage_rows(&[0; 19]));
+    assert_eq!(board.garbage_rows(), 20);
+}
+
+#[test]
+fn dig_games_start_with_garbage_and_keep_it_through_a_save() {
+    let engine = dig_engine(8, Some(1000.0));
+    assert_eq!(engine.board().garbage_rows(), 8);
+    assert!(!engine.is_game_over());
+
+    let mut restored = Engine::restore(&engine.snapshot()).unwrap();
+    let mut engine = engine;
+    for _ in 0..3 {
+        engine.tick(600.0);
+        restored.tick(600.0);
+    }
+
+    assert_eq!(engine.board().garbage_rows(), 9);
+    assert_eq!(restored.snapshot(), engine.snapshot());
+}
+
+#[test]
+fn clearing_every_garbage_row_finishes_the_dig() {
+    let engine = dig_engine(1, None);
+    let mut snapshot = engine.snapshot();
+    let bottom = snapshot.cells.len() - 1;
+    snapshot.cells[bottom] = vec![None; snapshot.cells[bottom].len()];
+    let landing: Vec<_> = Engine::restore(&snapshot)
+        .unwrap()
+        .ghost_piece()
+        .iter_blocks()
+        .collect();
+    for (col, cell) in snapshot.cells[bottom].iter_mut().enumerate() {
+        if !landing.contains(&(bottom as i8, col as i8)) {
+            *cell = Some(GARBAGE_COLOR.to_string());
+        }
+    }
+    let mut engine = Engine::restore(&snapshot).unwrap();
+    assert!(!engine.goal_reached());
+
+    engine.hard_drop();
+
+    assert_eq!(engine.board().garbage_rows(), 0);
+    assert!(engine.goal_reached());
+    assert!(engine.is_game_over());
+}
+
+#[test]
+fn starting_garbage_is_limited_to_the_board() {
+    let engine = dig_engine(u32::MAX, None);
+
+    assert_eq!(engine.board().garbage_rows(), 20);
+}
+
+#[test]
+fn rising_garbage_can_push_the_stack_out() {
+    let mut engine = dig_engine(19, Some(100.0));
+    for _ in 0..10 {
+        engine.tick(100.0);
+    }
+
+    assert_eq!(engine.top_out(), Some(TopOut::PushOut));
+}
@@ -0,0 +1,83 @@
+//! Native tests for DAS, ARR and soft drop handling.
+
+use tetris::{
+    engine::Engine,
+    input::{Direction, Handling, InputHandler},
+    random::RandomizerKind,
+    rotation::RotationSystemKind,
+};
+
+fn engine() -> Engine {
+    Engine::new(
+        0,
+        RandomizerKind::SevenBag.build(),
+        RotationSystemKind::Srs.build(),
+    )
+}
+
+fn handling(das_ms: f64, arr_ms: f64) -> Handling {
+    Handling {
+        das_ms,
+        arr_ms,
+        soft_drop_factor: 20.0,
+    }
+}
+
+#[test]
+fn pressing_shifts_once_and_repeats_only_after_das() {
+    let mut engine = engine();
+    let mut input = InputHandler::new(handling(100.0, 20.0));
+    let start = engine.current_piece().col();
+
+    input.press(&mut engine, Direction::Left);
+    assert_eq!(engine.current_piece().col(), start - 1);
+
+    input.update(&mut engine, 99.0);
+    assert_eq!(engine.current_piece().col(), start - 1);
+
+    input.update(&mut engine, 1.0);
+    assert_eq!(engine.current_piece().col(), start - 2);
+
+    input.update(&mut engine, 20.0);
+    assert_eq!(engine.current_piece().col(), start - 3);
+}
+
+#[test]
+fn zero_arr_shifts_straight_to_the_wall() {
+    let mut engine = engine();
+    let mut input = InputHandler::new(handling(100.0, 0.0));
+
+    input.press(&mut engine, Direction::Right);
+    input.update(&mut engine, 100.0);
+    assert!(!engine.move_right());
+}
+
+#[test]
+fn releasing_falls_back_to_the_other_held_direction() {
+    let mut engine = engine();
+    let mut input = InputHandler::new(handling(100.0, 20.0));
+    let start = engine.current_piece().col();
+
+    input.press(&mut engine, Direction::Left);
+    input.press(&mut engine, Direction::Right);
+    assert_eq!(engine.current_piece().col(), start);
+
+    input.release(&mut engine, Direction::Right);
+    assert_eq!(engine.current_piece().col(), start - 1);
+}
+
+#[test]
+fn held_soft_drop_falls_faster_than_gravity() {
+    let mut engine = engine();
+    let mut input = InputHandler::new(handling(100.0, 20.0));
+    let start = engine.current_piece().row();
+
+    let soft_drop_interval = engine.drop_interval_ms() / 20.0;
+    input.press_soft_drop(&mut engine);
+    input.update(&mut engine, soft_drop_interval * 3.0);
+    assert_eq!(engine.current_piece().row(), start + 4);
+
+    input.release_soft_drop();
+    input.update(&mut engine, 100.0);
+    assert_eq!(engine.current_piece().row(), start + 4);
+}
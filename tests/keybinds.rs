@@ -0,0 +1,39 @@
+//! Native tests for the key binding table.
+
+use tetris::keybinds::{Action, KeyBindings};
+
+#[test]
+fn binding_a_key_moves_it_to_the_new_action() {
+    let mut key_bindings = KeyBindings::default();
+    assert_eq!(key_bindings.action_for("KeyA"), Some(Action::MoveLeft));
+
+    key_bindings.bind("KeyA", Action::Hold);
+    assert_eq!(key_bindings.action_for("KeyA"), Some(Action::Hold));
+    assert!(!key_bindings
+        .keys_for(Action::MoveLeft)
+        .any(|code| code == "KeyA"));
+}
+
+#[test]
+fn bindings_round_trip_through_their_text_form() {
+    let mut key_bindings = KeyBindings::default();
+    key_bindings.unbind("Space");
+    key_bindings.bind("KeyJ", Action::HardDrop);
+
+    let serialized = key_bindings.serialize();
+    assert!(serialized.contains("hard_drop=KeyJ"));
+
+    let restored = KeyBindings::deserialize(&serialized).unwrap();
+    for action in Action::ALL.iter().copied() {
+        assert_eq!(
+            restored.keys_for(action).collect::<Vec<_>>(),
+            key_bindings.keys_for(action).collect::<Vec<_>>()
+        );
+    }
+}
+
+#[test]
+fn unknown_actions_are_rejected() {
+    assert!(KeyBindings::deserialize("teleport=KeyT").is_err());
+    assert!(KeyBindings::deserialize("hold").is_err());
+}
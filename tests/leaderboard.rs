@@ -0,0 +1,92 @@
+//! Native tests for the high-score table.
+
+use tetris::{
+    leaderboard::{HighScore, Leaderboard, LEADERBOARD_SIZE},
+    mode::GameMode,
+};
+
+fn high_score(score: u32) -> HighScore {
+    HighScore {
+        score,
+        lines: score / 100,
+        level: 1,
+        time_ms: 60_000.0,
+        date_ms: 1_700_000_000_000.0,
+        name: None,
+    }
+}
+
+#[test]
+fn keeps_the_best_results_in_order() {
+    let mut leaderboard = Leaderboard::new();
+
+    assert_eq!(
+        leaderboard.submit(GameMode::Endless, high_score(500)),
+        Some(0)
+    );
+    assert_eq!(
+        leaderboard.submit(GameMode::Endless, high_score(900)),
+        Some(0)
+    );
+    assert_eq!(
+        leaderboard.submit(GameMode::Endless, high_score(700)),
+        Some(1)
+    );
+    // Ties rank below the earlier result.
+    assert_eq!(
+        leaderboard.submit(GameMode::Endless, high_score(900)),
+        Some(1)
+    );
+
+    let scores: Vec<u32> = leaderboard
+        .entries(GameMode::Endless)
+        .iter()
+        .map(|entry| entry.score)
+        .collect();
+    assert_eq!(scores, vec![900, 900, 700, 500]);
+    assert_eq!(
+        leaderboard.personal_best(GameMode::Endless).unwrap().score,
+        900
+    );
+}
+
+#[test]
+fn drops_results_that_miss_the_table() {
+    let mut leaderboard = Leaderboard::new();
+    for score in 1..=LEADERBOARD_SIZE as u32 {
+        leaderboard.submit(GameMode::Endless, high_score(score * 100));
+    }
+
+    assert_eq!(leaderboard.submit(GameMode::Endless, high_score(50)), None);
+    assert_eq!(
+        leaderboard.submit(GameMode::Endless, high_score(150)),
+        Some(LEADERBOARD_SIZE - 1)
+    );
+    assert_eq!(
+        leaderboard.entries(GameMode::Endless).len(),
+        LEADERBOARD_SIZE
+    );
+}
+
+#[test]
+fn round_trips_through_text() {
+    let mut leaderboard = Leaderboard::new();
+    leaderboard.submit(GameMode::Endless, high_score(1200));
+    let mut named = high_score(800);
+    named.name = Some("  ada, the\nbest player ever ".to_string());
+    named.time_ms = 61_234.5;
+    leaderboard.submit(GameMode::Endless, named);
+
+    let serialized = leaderboard.serialize();
+    assert_eq!(
+        Leaderboard::deserialize(&serialized),
+        Ok(leaderboard.clone())
+    );
+    assert_eq!(
+        leaderboard.entries(GameMode::Endless)[1].name.as_deref(),
+        Some("ada, thebest pla")
+    );
+
+    assert!(Leaderboard::deserialize("normal,1,2").is_err());
+    assert!(Leaderboard::deserialize("sprint,1,2,3,4,5,").is_err());
+}
@@ -0,0 +1,54 @@
+//! Native tests for the piece randomizers.
+
+use tetris::{
+    pieces::PieceType,
+    random::{RandomizerKind, Rng},
+};
+
+fn deal(kind: RandomizerKind, seed: u32, count: usize) -> Vec<PieceType> {
+    let mut rng = Rng::new(seed);
+    let mut randomizer = kind.build();
+    (0..count).map(|_| randomizer.next(&mut rng)).collect()
+}
+
+#[test]
+fn bags_deal_every_piece_once_per_bag() {
+    for (kind, copies) in [
+        (RandomizerKind::SevenBag, 1),
+        (RandomizerKind::FourteenBag, 2),
+    ] {
+        let size = 7 * copies;
+        let pieces = deal(kind, 7, size * 3);
+        for bag in pieces.chunks(size) {
+            for piece_type in PieceType::ALL.iter() {
+                assert_eq!(bag.iter().filter(|&p| p == piece_type).count(), copies);
+            }
+        }
+    }
+}
+
+#[test]
+fn tgm_history_never_starts_with_s_z_or_o() {
+    for seed in 0..50 {
+        let first = deal(RandomizerKind::TgmHistory, seed, 1).remove(0);
+        assert!(![PieceType::S, PieceType::Z, PieceType::Square].contains(&first));
+    }
+}
+
+#[test]
+fn every_randomizer_is_reproducible_and_deals_every_piece() {
+    for kind in [
+        RandomizerKind::SevenBag,
+        RandomizerKind::FourteenBag,
+        RandomizerKind::PureRandom,
+        RandomizerKind::Nes,
+        RandomizerKind::TgmHistory,
+    ] {
+        let pieces = deal(kind, 1234, 200);
+        assert_eq!(pieces, deal(kind, 1234, 200));
+        for piece_type in PieceType::ALL.iter() {
+            assert!(pieces.contains(piece_type), "{:?}", kind);
+        }
+        assert_eq!(kind.build().kind(), kind);
+    }
+}
@@ -0,0 +1,240 @@
+//! Native tests for recording and replaying games.
+
+use tetris::{
+    engine::{Engine, Rules},
+    input::{Handling, InputHandler},
+    keybinds::Action,
+    random::RandomizerKind,
+    replay::{apply_input, Replay, ReplayInput, ReplayPlayer},
+    rotation::RotationSystemKind,
+    timestep::STEP_MS,
+};
+
+/// Plays a short scripted game the way `Game` does and returns the
+/// recording with the final engine.
+fn play() -> (Replay, Engine) {
+    let mut engine = Engine::new(
+        7,
+        RandomizerKind::SevenBag.build(),
+        RotationSystemKind::Srs.build(),
+    );
+    let mut input = InputHandler::new(Handling::default());
+    let mut replay = Replay::start(&engine, input.handling());
+
+    let script = [
+        (3, ReplayInput::Press(Action::MoveLeft)),
+        (30, ReplayInput::Release(Action::MoveLeft)),
+        (31, ReplayInput::Press(Action::RotateClockwise)),
+        (40, ReplayInput::Press(Action::HardDrop)),
+        (41, ReplayInput::Press(Action::SoftDrop)),
+        (90, ReplayInput::ReleaseAll),
+        (95, ReplayInput::Press(Action::Hold)),
+        (96, ReplayInput::Press(Action::HardDrop)),
+    ];
+
+    let mut events = script.iter().peekable();
+    for frame in 0..200 {
+        while let Some(&&(event_frame, event)) = events.peek() {
+            if event_frame > frame {
+                break;
+            }
+            replay.record(frame, event);
+            apply_input(&mut engine, &mut input, event);
+            events.next();
+        }
+        input.update(&mut engine, STEP_MS);
+        engine.tick(STEP_MS);
+    }
+    replay.frames = 200;
+
+    (replay, engine)
+}
+
+fn cells(engine: &Engine) -> Vec<Option<String>> {
+    let board = engine.board();
+    let mut cells = Vec::new();
+    for row in 0..board.height as usize {
+        for col in 0..board.width as usize {
+            cells.push(board.cell(row, col).map(str::to_string));
+        }
+    }
+    cells
+}
+
+#[test]
+fn playback_reproduces_the_game() {
+    let (replay, engine) = play();
+    let mut player = ReplayPlayer::new(replay);
+
+    player.seek(u64::MAX);
+
+    assert_eq!(player.frame(), 200);
+    assert_eq!(player.engine().stats(), engine.stats());
+    assert_eq!(cells(player.engine()), cells(&engine));
+    assert_eq!(
+        player.engine().current_piece().piece_type(),
+        engine.current_piece().piece_type()
+    );
+}
+
+#[test]
+fn playback_reports_the_games_clears() {
+    let mut engine = Engine::with_rules(
+        3,
+        RandomizerKind::SevenBag.build(),
+        RotationSystemKind::Srs.build(),
+        Rules {
+            garbage_rows: 1,
+            ..Rules::default()
+        },
+    );
+    let mut input = InputHandler::new(Handling::default());
+    let mut replay = Replay::start(&engine, input.handling());
+
+    // Find where the first piece drops into the garbage row's hole.
+    let hole = (0..10).find(|&col| engine.board().cell(19, col).is_none());
+    let hole = (19, hole.unwrap() as i8);
+    let (turns, shift) = (0..4)
+        .flat_map(|turns| (-5..=5).map(move |shift| (turns, shift)))
+        .find(|&(turns, shift)| {
+            let mut trial = Engine::restore(&engine.snapshot()).unwrap();
+            for _ in 0..turns {
+                trial.rotate_clockwise();
+            }
+            for _ in 0..i8::abs(shift) {
+                if shift < 0 {
+                    trial.move_left();
+                } else {
+                    trial.move_right();
+                }
+            }
+            trial.ghost_piece().iter_blocks().any(|block| block == hole)
+        })
+        .expect("the first piece should fit the hole");
+    let direction = if shift < 0 {
+        Action::MoveLeft
+    } else {
+        Action::MoveRight
+    };
+    let mut script = vec![ReplayInput::Press(Action::RotateClockwise); turns];
+    for _ in 0..i8::abs(shift) {
+        script.push(ReplayInput::Press(direction));
+        script.push(ReplayInput::Release(direction));
+    }
+    script.push(ReplayInput::Press(Action::HardDrop));
+    for event in script {
+        replay.record(0, event);
+        apply_input(&mut engine, &mut input, event);
+    }
+    replay.frames = 1;
+    let clear_events = engine.take_clear_events();
+    assert_eq!(clear_events.len(), 1);
+
+    let mut player = ReplayPlayer::new(replay);
+    assert_eq!(player.step(), clear_events);
+}
+
+#[test]
+fn seeking_backwards_matches_playing_forwards() {
+    let (replay, _) = play();
+    let mut forwards = ReplayPlayer::new(replay.clone());
+    forwards.seek(60);
+
+    let mut backwards = ReplayPlayer::new(replay);
+    backwards.seek(150);
+    backwards.seek(60);
+
+    assert_eq!(backwards.frame(), 60);
+    assert_eq!(cells(backwards.engine()), cells(forwards.engine()));
+    assert_eq!(backwards.engine().stats(), forwards.engine().stats());
+}
+
+#[test]
+fn speed_scales_playback() {
+    let (replay, _) = play();
+    let mut player = ReplayPlayer::new(replay);
+    player.set_speed(2.0);
+
+    player.advance(STEP_MS * 10.0);
+    assert_eq!(player.frame(), 20);
+
+    player.set_speed(0.0);
+    player.advance(1000.0);
+    assert_eq!(player.frame(), 20);
+}
+
+#[test]
+fn binary_format_round_trips() {
+    let (mut replay, _) = play();
+    replay.rules.preview_count = 3;
+    replay.handling.arr_ms = 0.0;
+    replay.events.push(tetris::replay::ReplayEvent {
+        frame: 1_000_000,
+        input: ReplayInput::Release(Action::Pause),
+    });
+
+    assert_eq!(Replay::from_bytes(&replay.to_bytes()), Ok(replay.clone()));
+    assert_eq!(Replay::from_base64(&replay.to_base64()), Ok(replay));
+}
+
+#[test]
+fn base64_is_url_safe_and_compact() {
+    let (replay, _) = play();
+    let text = replay.to_base64();
+
+    assert!(text
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'));
+    assert!(text.len() < 100, "{} is too long", text);
+}
+
+#[test]
+fn rejects_bad_input() {
+    let (replay, _) = play();
+    let bytes = replay.to_bytes();
+
+    assert!(Replay::from_bytes(b"nope").is_err());
+    assert!(Replay::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+
+    let mut future = bytes.clone();
+    future[3] = tetris::replay::REPLAY_FORMAT_VERSION + 1;
+    assert!(Replay::from_bytes(&future).is_err());
+
+    assert!(Replay::from_base64("not base64!").is_err());
+}
+
+#[test]
+fn rejects_rules_out_of_range() {
+    let (replay, _) = play();
+    let mut rules = replay.rules;
+    rules.garbage_rows = u32::MAX;
+    let mut out_of_range = vec![rules];
+    for edit in [
+        |rules: &mut Rules| rules.preview_count = 0,
+        |rules: &mut Rules| rules.preview_count = 7,
+        |rules: &mut Rules| rules.lock_delay_ms = -1.0,
+        |rules: &mut Rules| rules.lock_delay_ms = f64::NAN,
+        |rules: &mut Rules| rules.garbage_interval_ms = Some(f64::INFINITY),
+        |rules: &mut Rules| rules.start_level = u32::MAX,
+        |rules: &mut Rules| {
+            rules.start_level = 10;
+            rules.max_level = Some(5);
+        },
+    ] {
+        let mut rules = replay.rules;
+        edit(&mut rules);
+        out_of_range.push(rules);
+    }
+
+    for rules in out_of_range {
+        let bad = Replay {
+            rules,
+            ..replay.clone()
+        };
+        assert!(
+            Replay::from_base64(&bad.to_base64()).is_err(),
+            "{:?}",
+            rules
+        );
+    }
+}
@@ -0,0 +1,181 @@
+//! Native tests for saving and restoring games in progress.
+
+use tetris::{
+    engine::Engine,
+    input::{Handling, InputHandler},
+    keybinds::Action,
+    mode::GameMode,
+    random::RandomizerKind,
+    replay::{apply_input, Replay, ReplayInput, ReplayPlayer},
+    rotation::RotationSystemKind,
+    save::SavedGame,
+    timestep::STEP_MS,
+};
+
+fn engine_mid_game(randomizer: RandomizerKind) -> Engine {
+    let mut engine = Engine::new(42, randomizer.build(), RotationSystemKind::Srs.build());
+    for _ in 0..6 {
+        engine.move_left();
+        engine.hard_drop();
+    }
+    engine.hold();
+    engine.rotate_clockwise();
+    engine.move_right();
+    engine.soft_drop();
+    engine.tick(120.0);
+    engine
+}
+
+/// Drives two engines through the same moves for a few pieces.
+fn play_on(engine: &mut Engine) {
+    for i in 0..10 {
+        if i % 2 == 0 {
+            engine.move_right();
+            engine.rotate_counter_clockwise();
+        }
+        engine.hold();
+        engine.hard_drop();
+        engine.tick(700.0);
+    }
+}
+
+#[test]
+fn restored_engine_matches_the_original() {
+    for randomizer in [RandomizerKind::SevenBag, RandomizerKind::TgmHistory] {
+        let engine = engine_mid_game(randomizer);
+        let snapshot = engine.snapshot();
+        let restored = Engine::restore(&snapshot).unwrap();
+
+        assert_eq!(restored.snapshot(), snapshot);
+    }
+}
+
+#[test]
+fn restored_engine_deals_the_same_pieces() {
+    let mut engine = engine_mid_game(RandomizerKind::SevenBag);
+    let mut restored = Engine::restore(&engine.snapshot()).unwrap();
+
+    play_on(&mut engine);
+    play_on(&mut restored);
+
+    assert_eq!(restored.snapshot(), engine.snapshot());
+}
+
+#[test]
+fn saved_game_round_trips_through_base64() {
+    let engine = engine_mid_game(RandomizerKind::Nes);
+    let mut replay = Replay::start(&engine, Handling::default());
+    replay.record(3, ReplayInput::Press(Action::MoveLeft));
+    let saved = SavedGame {
+        mode: GameMode::Endless,
+        engine: engine.snapshot(),
+        replay,
+        frame: 600,
+        finesse_faults: 3,
+    };
+
+    assert_eq!(SavedGame::from_base64(&saved.to_base64()), Ok(saved));
+}
+
+#[test]
+fn rejects_corrupt_saves() {
+    let engine = engine_mid_game(RandomizerKind::SevenBag);
+    let saved = SavedGame {
+        mode: GameMode::Endless,
+        engine: engine.snapshot(),
+        replay: Replay::start(&engine, Handling::default()),
+        frame: 0,
+        finesse_faults: 0,
+    };
+    let bytes = saved.to_bytes();
+
+    assert!(SavedGame::from_bytes(&bytes[..bytes.len() / 2]).is_err());
+
+    let mut snapshot = engine.snapshot();
+    snapshot.cells.pop();
+    assert!(Engine::restore(&snapshot).is_err());
+
+    for pieces_drawn in [0, u64::MAX] {
+        let mut snapshot = engine.snapshot();
+        snapshot.pieces_drawn = pieces_drawn;
+        assert!(Engine::restore(&snapshot).is_err());
+    }
+
+    let mut snapshot = engine.snapshot();
+    snapshot.garbage_drawn = u64::MAX;
+    assert!(Engine::restore(&snapshot).is_err());
+}
+
+/// A live game the way `Game` runs it: inputs are recorded at the current
+/// frame, then logic steps run.
+struct LiveGame {
+    engine: Engine,
+    input: InputHandler,
+    recording: Replay,
+    frame: u64,
+}
+
+impl LiveGame {
+    fn send(&mut self, input: ReplayInput) {
+        self.recording.record(self.frame, input);
+        apply_input(&mut self.engine, &mut self.input, input);
+    }
+
+    fn run_until(&mut self, frame: u64) {
+        while self.frame < frame {
+            self.input.update(&mut self.engine, STEP_MS);
+            self.engine.tick(STEP_MS);
+            self.engine.take_clear_events();
+            self.frame += 1;
+        }
+    }
+}
+
+#[test]
+fn continued_game_matches_its_replay() {
+    let engine = Engine::new(
+        9,
+        RandomizerKind::SevenBag.build(),
+        RotationSystemKind::Srs.build(),
+    );
+    let input = InputHandler::new(Handling::default());
+    let mut game = LiveGame {
+        recording: Replay::start(&engine, input.handling()),
+        engine,
+        input,
+        frame: 0,
+    };
+    game.send(ReplayInput::Press(Action::MoveLeft));
+    game.run_until(5);
+    game.send(ReplayInput::Release(Action::MoveLeft));
+    game.send(ReplayInput::Press(Action::HardDrop));
+    game.run_until(50);
+    // The page is closed while a key is still held.
+    game.send(ReplayInput::Press(Action::MoveRight));
+    game.run_until(60);
+    let saved = SavedGame {
+        mode: GameMode::Endless,
+        engine: game.engine.snapshot(),
+        replay: game.recording.clone(),
+        frame: game.frame,
+        finesse_faults: 0,
+    };
+
+    // Continue it the way `Game::continue_game` does.
+    let saved = SavedGame::from_base64(&saved.to_base64()).unwrap();
+    let mut game = LiveGame {
+        engine: Engine::restore(&saved.engine).unwrap(),
+        input: InputHandler::new(saved.replay.handling),
+        recording: saved.replay,
+        frame: saved.frame,
+    };
+    game.send(ReplayInput::ReleaseAll);
+    game.run_until(100);
+    game.send(ReplayInput::Press(Action::RotateClockwise));
+    game.run_until(130);
+    game.recording.frames = game.frame;
+
+    let mut player = ReplayPlayer::new(game.recording.clone());
+    player.seek(game.frame);
+    assert_eq!(player.engine().snapshot(), game.engine.snapshot());
+}
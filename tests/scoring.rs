@@ -0,0 +1,62 @@
+//! Native tests for guideline scoring.
+
+use tetris::scoring::{Scoring, Spin};
+
+#[test]
+fn line_clears_scale_with_level() {
+    let mut scoring = Scoring::new();
+    let event = scoring.score_lock(4, Spin::None, false, 3).unwrap();
+    assert_eq!(event.points, 800 * 3);
+    assert_eq!(event.label(), "TETRIS");
+}
+
+#[test]
+fn consecutive_difficult_clears_are_back_to_back() {
+    let mut scoring = Scoring::new();
+    scoring.score_lock(4, Spin::None, false, 1);
+    scoring.score_lock(0, Spin::None, false, 1);
+
+    let event = scoring.score_lock(2, Spin::Full, false, 1).unwrap();
+    assert!(event.back_to_back);
+    assert_eq!(event.points, 1200 * 3 / 2);
+    assert_eq!(event.label(), "B2B T-SPIN DOUBLE");
+
+    scoring.score_lock(1, Spin::None, false, 1);
+    let event = scoring.score_lock(4, Spin::None, false, 1).unwrap();
+    assert!(!event.back_to_back);
+}
+
+#[test]
+fn combos_add_points_until_a_lock_clears_nothing() {
+    let mut scoring = Scoring::new();
+    assert_eq!(
+        scoring.score_lock(1, Spin::None, false, 1).unwrap().combo,
+        0
+    );
+    assert_eq!(
+        scoring.score_lock(1, Spin::None, false, 1).unwrap().points,
+        150
+    );
+
+    assert!(scoring.score_lock(0, Spin::None, false, 1).is_none());
+    assert_eq!(
+        scoring.score_lock(1, Spin::None, false, 1).unwrap().combo,
+        0
+    );
+}
+
+#[test]
+fn t_spins_without_lines_still_score() {
+    let mut scoring = Scoring::new();
+    let event = scoring.score_lock(0, Spin::Mini, false, 2).unwrap();
+    assert_eq!(event.points, 200);
+    assert_eq!(event.label(), "T-SPIN MINI");
+}
+
+#[test]
+fn perfect_clears_add_a_bonus() {
+    let mut scoring = Scoring::new();
+    let event = scoring.score_lock(2, Spin::None, true, 1).unwrap();
+    assert_eq!(event.points, 300 + 1200);
+    assert_eq!(event.label(), "DOUBLE PERFECT CLEAR");
+}
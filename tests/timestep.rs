@@ -0,0 +1,26 @@
+//! Native tests for the fixed logic timestep.
+
+use tetris::timestep::{FixedTimestep, STEP_MS};
+
+#[test]
+fn carries_partial_steps_between_frames() {
+    let mut timestep = FixedTimestep::new();
+
+    assert_eq!(timestep.advance(STEP_MS * 0.5), 0);
+    assert!((timestep.alpha() - 0.5).abs() < 1e-9);
+    assert_eq!(timestep.advance(STEP_MS * 0.75), 1);
+    assert!((timestep.alpha() - 0.25).abs() < 1e-9);
+    assert_eq!(timestep.frame(), 1);
+}
+
+#[test]
+fn refresh_rate_does_not_change_step_count() {
+    let mut at_60_hz = FixedTimestep::new();
+    let mut at_144_hz = FixedTimestep::new();
+
+    let steps_60: u32 = (0..60).map(|_| at_60_hz.advance(1000.0 / 60.0)).sum();
+    let steps_144: u32 = (0..144).map(|_| at_144_hz.advance(1000.0 / 144.0)).sum();
+
+    assert!((59..=60).contains(&steps_60));
+    assert!((59..=60).contains(&steps_144));
+}
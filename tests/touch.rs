@@ -0,0 +1,54 @@
+//! Native tests for touch gesture recognition.
+
+use tetris::touch::{Gesture, GestureConfig, GestureRecognizer};
+
+fn recognizer() -> GestureRecognizer {
+    GestureRecognizer::new(GestureConfig::for_cell_size(20.0))
+}
+
+#[test]
+fn short_touch_is_a_tap() {
+    let mut gestures = recognizer();
+    gestures.start(100.0, 100.0, 0.0);
+
+    assert!(gestures.move_to(103.0, 102.0).is_empty());
+    assert_eq!(gestures.end(103.0, 102.0, 120.0), vec![Gesture::Tap]);
+}
+
+#[test]
+fn long_press_is_not_a_tap() {
+    let mut gestures = recognizer();
+    gestures.start(100.0, 100.0, 0.0);
+
+    assert!(gestures.end(100.0, 100.0, 600.0).is_empty());
+}
+
+#[test]
+fn horizontal_drag_shifts_once_per_cell() {
+    let mut gestures = recognizer();
+    gestures.start(100.0, 100.0, 0.0);
+
+    assert_eq!(gestures.move_to(125.0, 105.0), vec![Gesture::ShiftRight]);
+    assert_eq!(
+        gestures.move_to(170.0, 130.0),
+        vec![Gesture::ShiftRight, Gesture::ShiftRight]
+    );
+    assert_eq!(gestures.move_to(95.0, 130.0).len(), 3);
+    // Locked to the horizontal axis, so the final position is no flick.
+    assert_eq!(gestures.end(95.0, 300.0, 100.0), vec![]);
+}
+
+#[test]
+fn slow_drag_down_soft_drops_and_flick_hard_drops() {
+    let mut gestures = recognizer();
+    gestures.start(100.0, 100.0, 0.0);
+    assert_eq!(
+        gestures.move_to(100.0, 145.0),
+        vec![Gesture::SoftDrop, Gesture::SoftDrop]
+    );
+    assert_eq!(gestures.end(100.0, 170.0, 1000.0), vec![Gesture::SoftDrop]);
+
+    gestures.start(100.0, 100.0, 0.0);
+    let flicked = gestures.end(100.0, 200.0, 50.0);
+    assert_eq!(flicked.last(), Some(&Gesture::HardDrop));
+}
@@ -1,4 +1,4 @@
-import init, { Game } from "wasm-tetris";
+import init, { Action, Game, GameMode, GameState } from "wasm-tetris";
 
 const sleep = ms => new Promise(r => setTimeout(r, ms));
 
@@ -9,53 +9,151 @@ async function run() {
 
   window.onresize = () => game.resize();
 
+  const replaySeek = document.querySelector("#replay-seek");
+
   let lastTime = 0;
   const renderLoop = (currentTime) => {
     const deltaTime = currentTime - lastTime;
     lastTime = currentTime;
 
     game.tick(deltaTime);
+    game.render();
+
+    if (game.state() === GameState.Replay) {
+      replaySeek.max = Number(game.replay_length());
+      replaySeek.value = Number(game.replay_frame());
+    }
 
     requestAnimationFrame(renderLoop);
   };
 
-  const normalButton = document.querySelector("#normal-button");
+  document.querySelectorAll(".menu-buttons [data-mode]").forEach((button) => {
+    button.addEventListener("click", async () => {
+      game.set_mode(GameMode[button.dataset.mode]);
+      game.hide_menu();
+      await sleep(500);
+      game.show_game();
+      await sleep(500);
+      game.resume_game();
+    });
+  });
+
+  const playerName = document.querySelector("#player-name");
+  playerName.value = game.player_name() ?? "";
+  playerName.addEventListener("change", () => game.set_player_name(playerName.value));
+  // Typing a name shouldn't move pieces.
+  playerName.addEventListener("keydown", (e) => e.stopPropagation());
 
-  normalButton.addEventListener("click", async (e) => {
+  document.querySelector("#continue-button").addEventListener("click", async () => {
+    try {
+      game.continue_game();
+    } catch (e) {
+      alert(`Could not continue: ${e}`);
+      return;
+    }
     game.hide_menu();
     await sleep(500);
     game.show_game();
+  });
+
+  addEventListener("pagehide", () => game.save_game());
+
+  document.querySelector("#resume-button").addEventListener("click", () => game.resume_game());
+  document.querySelector("#restart-button").addEventListener("click", () => game.restart_game());
+  document.querySelector("#quit-button").addEventListener("click", () => game.quit_to_menu());
+  document.querySelector("#retry-button").addEventListener("click", () => game.restart_game());
+  document.querySelector("#menu-button").addEventListener("click", () => game.quit_to_menu());
+
+  document.querySelector("#replay-button").addEventListener("click", () => game.watch_replay());
+  document.querySelector("#copy-replay-button").addEventListener("click", () => {
+    navigator.clipboard.writeText(game.export_replay());
+  });
+  document.querySelector("#load-replay-button").addEventListener("click", async () => {
+    const replay = prompt("Paste a replay");
+    if (!replay) {
+      return;
+    }
+    try {
+      game.load_replay(replay);
+    } catch (e) {
+      alert(`Could not load replay: ${e}`);
+      return;
+    }
+    game.hide_menu();
     await sleep(500);
-    game.resume_game();
-  })
+    game.show_game();
+  });
+  document.querySelector("#replay-exit-button").addEventListener("click", () => game.stop_replay());
+  replaySeek.addEventListener("input", () => game.seek_replay(BigInt(replaySeek.value)));
+  for (const button of document.querySelectorAll(".replay-speeds [data-speed]")) {
+    button.addEventListener("click", () => game.set_replay_speed(Number(button.dataset.speed)));
+  }
+
+  document.querySelector(".keybinds-list").addEventListener("click", (e) => {
+    const keybind = e.target.closest("[data-action]");
+    if (keybind) {
+      game.begin_rebind(Number(keybind.dataset.action));
+    }
+  });
+
+  const canvasContainer = document.querySelector(".canvas-container");
+  let touchId = null;
+
+  canvasContainer.addEventListener("touchstart", (e) => {
+    e.preventDefault();
+    if (touchId !== null) {
+      return;
+    }
+    const touch = e.changedTouches[0];
+    touchId = touch.identifier;
+    game.touch_start(touch.clientX, touch.clientY, e.timeStamp);
+  }, { passive: false });
+
+  canvasContainer.addEventListener("touchmove", (e) => {
+    e.preventDefault();
+    for (const touch of e.changedTouches) {
+      if (touch.identifier === touchId) {
+        game.touch_move(touch.clientX, touch.clientY);
+      }
+    }
+  }, { passive: false });
+
+  canvasContainer.addEventListener("touchend", (e) => {
+    for (const touch of e.changedTouches) {
+      if (touch.identifier === touchId) {
+        touchId = null;
+        game.touch_end(touch.clientX, touch.clientY, e.timeStamp);
+      }
+    }
+  });
+
+  canvasContainer.addEventListener("touchcancel", () => {
+    touchId = null;
+    game.touch_cancel();
+  });
+
+  for (const button of document.querySelectorAll(".touch-controls [data-action]")) {
+    const action = Action[button.dataset.action];
+    button.addEventListener("pointerdown", (e) => {
+      e.preventDefault();
+      button.setPointerCapture(e.pointerId);
+      game.action_down(action);
+    });
+    const release = () => game.action_up(action);
+    button.addEventListener("pointerup", release);
+    button.addEventListener("pointercancel", release);
+  }
 
   addEventListener("keydown", (e) => {
-    switch (e.code) {
-      case "KeyR":
-      case "ArrowUp":
-        game.rotate_current_piece();
-        break;
-      case "KeyA":
-      case "ArrowLeft":
-        game.move_cursor_left();
-        break;
-      case "KeyD":
-      case "ArrowRight":
-        game.move_cursor_right();
-        break;
-      case "Space":
-        game.hard_drop_current_piece();
-        break;
-      case "KeyC":
-      case "ShiftLeft":
-      case "ShiftRight":
-        game.hold_piece();
-        break;
-      case "KeyS":
-      case "ArrowDown":
-        game.soft_drop();
-        break;
+    // Auto-repeat is handled by DAS/ARR inside the game.
+    if (e.repeat) {
+      return;
     }
+    game.key_down(e.code);
+  });
+
+  addEventListener("keyup", (e) => {
+    game.key_up(e.code);
   });
 
   requestAnimationFrame(renderLoop);